@@ -0,0 +1,90 @@
+// Throughput of `TreiberStack` against the `Mutex<second::List>` it's meant to
+// replace. Run with `cargo bench --bench treiber`.
+//
+// Every thread does the same mix, push then pop, on one shared stack, so this
+// is the free-list pattern at its most contended.
+use std::hint::black_box;
+use std::sync::{Arc, Barrier, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use linked_lists_rs::second;
+use linked_lists_rs::treiber::TreiberStack;
+
+const OPS_PER_THREAD: usize = 200_000;
+
+trait SharedStack: Send + Sync + 'static {
+    fn push(&self, elem: usize);
+    fn pop(&self) -> Option<usize>;
+}
+
+impl SharedStack for TreiberStack<usize> {
+    fn push(&self, elem: usize) {
+        TreiberStack::push(self, elem)
+    }
+
+    fn pop(&self) -> Option<usize> {
+        TreiberStack::pop(self)
+    }
+}
+
+impl SharedStack for Mutex<second::List<usize>> {
+    fn push(&self, elem: usize) {
+        self.lock().unwrap().push(elem)
+    }
+
+    fn pop(&self) -> Option<usize> {
+        self.lock().unwrap().pop()
+    }
+}
+
+fn run<S: SharedStack>(stack: S, threads: usize) -> Duration {
+    let stack = Arc::new(stack);
+    let barrier = Arc::new(Barrier::new(threads + 1));
+    let handles: Vec<_> = (0..threads)
+        .map(|_| {
+            let stack = stack.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                barrier.wait();
+                for i in 0..OPS_PER_THREAD {
+                    stack.push(i);
+                    black_box(stack.pop());
+                }
+            })
+        })
+        .collect();
+
+    barrier.wait();
+    let start = Instant::now();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    start.elapsed()
+}
+
+fn report(name: &str, threads: usize, elapsed: Duration) {
+    let ops = (threads * OPS_PER_THREAD * 2) as f64;
+    println!(
+        "{:<24} {:>2} threads  {:>8.2} Mops/s",
+        name,
+        threads,
+        ops / elapsed.as_secs_f64() / 1e6
+    );
+}
+
+fn main() {
+    let max = thread::available_parallelism()
+        .map_or(4, |n| n.get())
+        .max(4);
+    let mut threads = 1;
+    while threads <= max {
+        report("TreiberStack", threads, run(TreiberStack::new(), threads));
+        report(
+            "Mutex<second::List>",
+            threads,
+            run(Mutex::new(second::List::new()), threads),
+        );
+        threads *= 2;
+    }
+}
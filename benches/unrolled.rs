@@ -0,0 +1,117 @@
+// `UnrolledList` against `sixth::LinkedList` on a large numeric sequence.
+// Run with `cargo bench --bench unrolled`.
+//
+// Each run builds the list, sums it front to back and back to front, updates
+// every element in place, and then pops it empty, timing each phase.
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use linked_lists_rs::sixth::LinkedList;
+use linked_lists_rs::unrolled::UnrolledList;
+
+const LEN: u64 = 1_000_000;
+const RUNS: u32 = 5;
+
+// Everything the benchmark needs, so both lists run the same code.
+trait Sequence: Default {
+    fn push_back(&mut self, elem: u64);
+    fn pop_front(&mut self) -> Option<u64>;
+    fn sum(&self) -> u64;
+    fn sum_rev(&self) -> u64;
+    fn bump(&mut self);
+}
+
+impl Sequence for LinkedList<u64> {
+    fn push_back(&mut self, elem: u64) {
+        LinkedList::push_back(self, elem)
+    }
+
+    fn pop_front(&mut self) -> Option<u64> {
+        LinkedList::pop_front(self)
+    }
+
+    fn sum(&self) -> u64 {
+        self.iter().sum()
+    }
+
+    fn sum_rev(&self) -> u64 {
+        self.iter().rev().sum()
+    }
+
+    fn bump(&mut self) {
+        self.iter_mut().for_each(|x| *x += 1)
+    }
+}
+
+impl<const N: usize> Sequence for UnrolledList<u64, N> {
+    fn push_back(&mut self, elem: u64) {
+        UnrolledList::push_back(self, elem)
+    }
+
+    fn pop_front(&mut self) -> Option<u64> {
+        UnrolledList::pop_front(self)
+    }
+
+    fn sum(&self) -> u64 {
+        self.iter().sum()
+    }
+
+    fn sum_rev(&self) -> u64 {
+        self.iter().rev().sum()
+    }
+
+    fn bump(&mut self) {
+        self.iter_mut().for_each(|x| *x += 1)
+    }
+}
+
+#[derive(Default)]
+struct Times {
+    push: Duration,
+    iter: Duration,
+    iter_mut: Duration,
+    pop: Duration,
+}
+
+fn time<R>(total: &mut Duration, f: impl FnOnce() -> R) -> R {
+    let start = Instant::now();
+    let result = f();
+    *total += start.elapsed();
+    result
+}
+
+fn run<S: Sequence>(name: &str) {
+    let mut times = Times::default();
+    for _ in 0..RUNS {
+        let mut list = S::default();
+        time(&mut times.push, || {
+            for i in 0..LEN {
+                list.push_back(i);
+            }
+        });
+        time(&mut times.iter, || {
+            black_box(list.sum());
+            black_box(list.sum_rev());
+        });
+        time(&mut times.iter_mut, || list.bump());
+        time(&mut times.pop, || {
+            while black_box(list.pop_front()).is_some() {}
+        });
+    }
+    let per_run = |d: Duration| d.as_secs_f64() * 1e3 / RUNS as f64;
+    println!(
+        "{:<22} push {:>7.2}ms  iter {:>7.2}ms  iter_mut {:>7.2}ms  pop {:>7.2}ms",
+        name,
+        per_run(times.push),
+        per_run(times.iter),
+        per_run(times.iter_mut),
+        per_run(times.pop),
+    );
+}
+
+fn main() {
+    run::<LinkedList<u64>>("sixth::LinkedList");
+    run::<UnrolledList<u64, 8>>("UnrolledList<_, 8>");
+    run::<UnrolledList<u64, 16>>("UnrolledList<_, 16>");
+    run::<UnrolledList<u64, 64>>("UnrolledList<_, 64>");
+}
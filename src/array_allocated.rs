@@ -0,0 +1,574 @@
+// A doubly linked list that lives entirely inside an inline `[_; N]` array.
+//
+// Links are indices into the array instead of pointers, and unused slots are
+// threaded together into a free list through their `back` link. Nothing here
+// touches the heap and only `core` is used, so it works in `no_std` code.
+// When every slot is taken, pushes hand the element back as `Err(elem)`.
+use core::fmt;
+use core::marker::PhantomData;
+
+type Link = Option<usize>;
+
+#[derive(Clone)]
+struct Node<T> {
+    elem: Option<T>,
+    front: Link,
+    back: Link,
+}
+
+#[derive(Clone)]
+pub struct ArrayLinkedList<T, const N: usize> {
+    nodes: [Node<T>; N],
+    front: Link,
+    back: Link,
+    free: Link,
+    len: usize,
+}
+
+impl<T, const N: usize> ArrayLinkedList<T, N> {
+    pub fn new() -> Self {
+        // Every slot starts out on the free list, in order.
+        let nodes = core::array::from_fn(|i| Node {
+            elem: None,
+            front: None,
+            back: if i + 1 < N { Some(i + 1) } else { None },
+        });
+        ArrayLinkedList {
+            nodes,
+            front: None,
+            back: None,
+            free: if N > 0 { Some(0) } else { None },
+            len: 0,
+        }
+    }
+
+    pub fn push_front(&mut self, elem: T) -> Result<(), T> {
+        let idx = self.alloc(elem)?;
+        self.link_between(None, self.front, idx);
+        Ok(())
+    }
+
+    pub fn push_back(&mut self, elem: T) -> Result<(), T> {
+        let idx = self.alloc(elem)?;
+        self.link_between(self.back, None, idx);
+        Ok(())
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.front.map(|idx| self.unlink(idx))
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.back.map(|idx| self.unlink(idx))
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.front.and_then(|idx| self.nodes[idx].elem.as_ref())
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.back.and_then(|idx| self.nodes[idx].elem.as_ref())
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.front.and_then(|idx| self.nodes[idx].elem.as_mut())
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        self.back.and_then(|idx| self.nodes[idx].elem.as_mut())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+
+    pub fn iter(&self) -> Iter<'_, T, N> {
+        Iter {
+            list: self,
+            front: self.front,
+            back: self.back,
+            len: self.len,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, N> {
+        IterMut {
+            nodes: self.nodes.as_mut_ptr(),
+            front: self.front,
+            back: self.back,
+            len: self.len,
+            _boo: PhantomData,
+        }
+    }
+
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T, N> {
+        CursorMut {
+            list: self,
+            cur: None,
+            index: None,
+        }
+    }
+
+    // Takes a slot off the free list and puts `elem` in it.
+    fn alloc(&mut self, elem: T) -> Result<usize, T> {
+        match self.free {
+            Some(idx) => {
+                let node = &mut self.nodes[idx];
+                self.free = node.back;
+                node.elem = Some(elem);
+                Ok(idx)
+            }
+            None => Err(elem),
+        }
+    }
+
+    // Links the allocated slot `idx` in between `front` and `back`, which
+    // must be adjacent (or `None` for the ends of the list).
+    fn link_between(&mut self, front: Link, back: Link, idx: usize) {
+        self.nodes[idx].front = front;
+        self.nodes[idx].back = back;
+        match front {
+            Some(front) => self.nodes[front].back = Some(idx),
+            None => self.front = Some(idx),
+        }
+        match back {
+            Some(back) => self.nodes[back].front = Some(idx),
+            None => self.back = Some(idx),
+        }
+        self.len += 1;
+    }
+
+    // Unlinks the slot `idx`, returns it to the free list and hands back its element.
+    fn unlink(&mut self, idx: usize) -> T {
+        let Node { front, back, .. } = self.nodes[idx];
+        match front {
+            Some(front) => self.nodes[front].back = back,
+            None => self.front = back,
+        }
+        match back {
+            Some(back) => self.nodes[back].front = front,
+            None => self.back = front,
+        }
+        self.len -= 1;
+
+        let node = &mut self.nodes[idx];
+        node.front = None;
+        node.back = self.free;
+        self.free = Some(idx);
+        node.elem.take().unwrap()
+    }
+}
+
+impl<T, const N: usize> Default for ArrayLinkedList<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for ArrayLinkedList<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+pub struct IntoIter<T, const N: usize> {
+    list: ArrayLinkedList<T, N>,
+}
+
+pub struct Iter<'a, T, const N: usize> {
+    list: &'a ArrayLinkedList<T, N>,
+    front: Link,
+    back: Link,
+    len: usize,
+}
+
+pub struct IterMut<'a, T, const N: usize> {
+    nodes: *mut Node<T>,
+    front: Link,
+    back: Link,
+    len: usize,
+    _boo: PhantomData<&'a mut T>,
+}
+
+impl<T, const N: usize> IntoIterator for ArrayLinkedList<T, N> {
+    type IntoIter = IntoIter<T, N>;
+    type Item = T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { list: self }
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a ArrayLinkedList<T, N> {
+    type IntoIter = Iter<'a, T, N>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut ArrayLinkedList<T, N> {
+    type IntoIter = IterMut<'a, T, N>;
+    type Item = &'a mut T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T, const N: usize> Iterator for IntoIter<T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.list.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.len, Some(self.list.len))
+    }
+}
+
+impl<T, const N: usize> DoubleEndedIterator for IntoIter<T, N> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.list.pop_back()
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for IntoIter<T, N> {}
+
+impl<'a, T, const N: usize> Iterator for Iter<'a, T, N> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len > 0 {
+            self.front.map(|idx| {
+                let node = &self.list.nodes[idx];
+                self.len -= 1;
+                self.front = node.back;
+                node.elem.as_ref().unwrap()
+            })
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T, const N: usize> DoubleEndedIterator for Iter<'a, T, N> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len > 0 {
+            self.back.map(|idx| {
+                let node = &self.list.nodes[idx];
+                self.len -= 1;
+                self.back = node.front;
+                node.elem.as_ref().unwrap()
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a, T, const N: usize> ExactSizeIterator for Iter<'a, T, N> {}
+
+impl<'a, T, const N: usize> Iterator for IterMut<'a, T, N> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len > 0 {
+            // SAFETY: every index in the list is in bounds, and each one is
+            // yielded at most once since `len` stops the two ends crossing.
+            self.front.map(|idx| unsafe {
+                let node = &mut *self.nodes.add(idx);
+                self.len -= 1;
+                self.front = node.back;
+                node.elem.as_mut().unwrap()
+            })
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T, const N: usize> DoubleEndedIterator for IterMut<'a, T, N> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len > 0 {
+            self.back.map(|idx| unsafe {
+                let node = &mut *self.nodes.add(idx);
+                self.len -= 1;
+                self.back = node.front;
+                node.elem.as_mut().unwrap()
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a, T, const N: usize> ExactSizeIterator for IterMut<'a, T, N> {}
+
+unsafe impl<'a, T: Send, const N: usize> Send for IterMut<'a, T, N> {}
+unsafe impl<'a, T: Sync, const N: usize> Sync for IterMut<'a, T, N> {}
+
+// Same shape as `sixth::CursorMut`: there's a "ghost" position between the
+// back and the front of the list where `current` is `None`.
+pub struct CursorMut<'a, T, const N: usize> {
+    list: &'a mut ArrayLinkedList<T, N>,
+    cur: Link,
+    index: Option<usize>,
+}
+
+impl<'a, T, const N: usize> CursorMut<'a, T, N> {
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    pub fn move_next(&mut self) {
+        if let Some(cur) = self.cur {
+            self.cur = self.list.nodes[cur].back;
+            self.index = self.cur.and_then(|_| self.index.map(|index| index + 1));
+        } else if !self.list.is_empty() {
+            self.cur = self.list.front;
+            self.index = Some(0);
+        }
+    }
+
+    pub fn move_prev(&mut self) {
+        if let Some(cur) = self.cur {
+            self.cur = self.list.nodes[cur].front;
+            self.index = self.cur.and_then(|_| self.index.map(|index| index - 1));
+        } else if !self.list.is_empty() {
+            self.cur = self.list.back;
+            self.index = Some(self.list.len - 1);
+        }
+    }
+
+    pub fn current(&mut self) -> Option<&mut T> {
+        let cur = self.cur?;
+        self.list.nodes[cur].elem.as_mut()
+    }
+
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        let next = match self.cur {
+            Some(cur) => self.list.nodes[cur].back,
+            None => self.list.front,
+        }?;
+        self.list.nodes[next].elem.as_mut()
+    }
+
+    pub fn peek_prev(&mut self) -> Option<&mut T> {
+        let prev = match self.cur {
+            Some(cur) => self.list.nodes[cur].front,
+            None => self.list.back,
+        }?;
+        self.list.nodes[prev].elem.as_mut()
+    }
+
+    // Inserts before the current element. On the ghost, that's the back.
+    pub fn insert_before(&mut self, elem: T) -> Result<(), T> {
+        let idx = self.list.alloc(elem)?;
+        match self.cur {
+            Some(cur) => {
+                let prev = self.list.nodes[cur].front;
+                self.list.link_between(prev, Some(cur), idx);
+                *self.index.as_mut().unwrap() += 1;
+            }
+            None => self.list.link_between(self.list.back, None, idx),
+        }
+        Ok(())
+    }
+
+    // Inserts after the current element. On the ghost, that's the front.
+    pub fn insert_after(&mut self, elem: T) -> Result<(), T> {
+        let idx = self.list.alloc(elem)?;
+        match self.cur {
+            Some(cur) => {
+                let next = self.list.nodes[cur].back;
+                self.list.link_between(Some(cur), next, idx);
+            }
+            None => self.list.link_between(None, self.list.front, idx),
+        }
+        Ok(())
+    }
+
+    // Removes the current element and moves on to the next one.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let cur = self.cur?;
+        self.cur = self.list.nodes[cur].back;
+        if self.cur.is_none() {
+            self.index = None;
+        }
+        Some(self.list.unlink(cur))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ArrayLinkedList;
+
+    #[test]
+    fn basics() {
+        let mut list: ArrayLinkedList<i32, 4> = ArrayLinkedList::new();
+        assert_eq!(list.capacity(), 4);
+        assert_eq!(list.pop_front(), None);
+        assert_eq!(list.pop_back(), None);
+
+        assert_eq!(list.push_back(2), Ok(()));
+        assert_eq!(list.push_back(3), Ok(()));
+        assert_eq!(list.push_front(1), Ok(()));
+        assert_eq!(list.push_front(0), Ok(()));
+        assert!(list.is_full());
+
+        // Full lists hand the element back
+        assert_eq!(list.push_back(4), Err(4));
+        assert_eq!(list.push_front(-1), Err(-1));
+
+        assert_eq!(list.front(), Some(&0));
+        assert_eq!(list.back(), Some(&3));
+        *list.back_mut().unwrap() = 30;
+
+        assert_eq!(list.pop_front(), Some(0));
+        assert_eq!(list.pop_back(), Some(30));
+        assert_eq!(list.len(), 2);
+
+        // Freed slots get reused
+        assert_eq!(list.push_back(5), Ok(()));
+        assert_eq!(list.push_front(6), Ok(()));
+        assert_eq!(list.push_front(7), Err(7));
+        assert_eq!(format!("{:?}", list), "[6, 1, 2, 5]");
+
+        list.clear();
+        assert!(list.is_empty());
+        assert_eq!(list.front(), None);
+        assert_eq!(list.back(), None);
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let mut list: ArrayLinkedList<i32, 0> = ArrayLinkedList::new();
+        assert!(list.is_full());
+        assert_eq!(list.push_back(1), Err(1));
+        assert_eq!(list.pop_front(), None);
+    }
+
+    #[test]
+    fn iterators() {
+        let mut list: ArrayLinkedList<i32, 8> = ArrayLinkedList::new();
+        for x in 0..5 {
+            list.push_back(x).unwrap();
+        }
+
+        let mut iter = list.iter();
+        assert_eq!(iter.len(), 5);
+        assert_eq!(iter.next(), Some(&0));
+        assert_eq!(iter.next_back(), Some(&4));
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.collect::<Vec<_>>(), &[&1, &2, &3]);
+
+        for x in &mut list {
+            *x *= 10;
+        }
+        let mut iter = list.iter_mut();
+        assert_eq!(iter.next_back(), Some(&mut 40));
+        assert_eq!(iter.next(), Some(&mut 0));
+
+        let cloned = list.clone();
+        assert_eq!(
+            cloned.into_iter().rev().collect::<Vec<_>>(),
+            &[40, 30, 20, 10, 0]
+        );
+        assert_eq!(list.into_iter().collect::<Vec<_>>(), &[0, 10, 20, 30, 40]);
+    }
+
+    #[test]
+    fn cursor() {
+        let mut list: ArrayLinkedList<i32, 6> = ArrayLinkedList::new();
+        for x in [1, 2, 3] {
+            list.push_back(x).unwrap();
+        }
+
+        let mut cursor = list.cursor_mut();
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.peek_next(), Some(&mut 1));
+        assert_eq!(cursor.peek_prev(), Some(&mut 3));
+
+        cursor.move_next();
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(cursor.insert_before(0), Ok(()));
+        assert_eq!(cursor.index(), Some(1));
+        assert_eq!(cursor.insert_after(10), Ok(()));
+        assert_eq!(cursor.peek_next(), Some(&mut 10));
+
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&mut 2));
+        assert_eq!(cursor.remove_current(), Some(2));
+        assert_eq!(cursor.current(), Some(&mut 3));
+        assert_eq!(cursor.index(), Some(3));
+        assert_eq!(cursor.remove_current(), Some(3));
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.index(), None);
+
+        // On the ghost, before is the back and after is the front
+        assert_eq!(cursor.insert_before(4), Ok(()));
+        assert_eq!(cursor.insert_after(-1), Ok(()));
+        assert_eq!(cursor.insert_after(-2), Ok(()));
+        assert_eq!(cursor.insert_after(-3), Err(-3));
+
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&mut 4));
+        assert_eq!(cursor.index(), Some(5));
+        assert_eq!(cursor.peek_prev(), Some(&mut 10));
+
+        assert_eq!(
+            list.iter().copied().collect::<Vec<_>>(),
+            &[-2, -1, 0, 1, 10, 4]
+        );
+    }
+
+    #[test]
+    fn cursor_walk_back_to_ghost() {
+        let mut list: ArrayLinkedList<i32, 2> = ArrayLinkedList::new();
+        list.push_back(1).unwrap();
+        list.push_back(2).unwrap();
+
+        let mut cursor = list.cursor_mut();
+        cursor.move_next();
+        assert_eq!(cursor.index(), Some(0));
+        cursor.move_prev();
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.index(), None);
+
+        // And wrapping forwards off the back lands on the ghost too
+        cursor.move_prev();
+        assert_eq!(cursor.index(), Some(1));
+        cursor.move_next();
+        assert_eq!(cursor.index(), None);
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&mut 1));
+    }
+}
@@ -0,0 +1,320 @@
+// Conversions between the lists in this crate, and to/from `Vec`, `VecDeque`
+// and `std::collections::LinkedList`.
+//
+// Every conversion keeps iteration order: the front of the source (its head,
+// or the top of a stack) becomes the front of the result. So a `second::List`
+// turns into a `Vec` with the most recently pushed element at index 0, and a
+// `Vec` turns into a `second::List` whose `peek` is the `Vec`'s first element.
+//
+// `second::List`, `double::stack::Stack` and `fifth::UnsafeQueue` share the
+// same `#[repr(C)]` node layout, so converting between those three hands over
+// the existing nodes instead of reallocating them. Everything else moves one
+// element at a time.
+//
+// `third::List` shares its nodes with other lists, so it can only be converted
+// into. `array_allocated::ArrayLinkedList` can run out of room, so it has no
+// `From` impls at all.
+use std::collections::{LinkedList as StdLinkedList, VecDeque};
+
+use crate::double::stack::Stack;
+use crate::{fifth, first, fourth, second, sixth, third};
+
+// Builds a list that yields the items in the same order `iter` did.
+pub(crate) trait FromFront<T> {
+    fn from_front<I: Iterator<Item = T>>(iter: I) -> Self;
+}
+
+impl<T> FromFront<T> for first::List<T> {
+    fn from_front<I: Iterator<Item = T>>(iter: I) -> Self {
+        iter.collect()
+    }
+}
+
+impl<T> FromFront<T> for second::List<T> {
+    fn from_front<I: Iterator<Item = T>>(iter: I) -> Self {
+        let mut list = second::List::new();
+        for elem in iter {
+            list.push(elem);
+        }
+        list.reverse();
+        list
+    }
+}
+
+impl<T> FromFront<T> for third::List<T> {
+    fn from_front<I: Iterator<Item = T>>(iter: I) -> Self {
+        // Persistent lists only grow at the front, so start from the back.
+        let elems: Vec<T> = iter.collect();
+        let mut list = third::List::new();
+        for elem in elems.into_iter().rev() {
+            list = list.prepend(elem);
+        }
+        list
+    }
+}
+
+impl<T> FromFront<T> for fourth::Deque<T> {
+    fn from_front<I: Iterator<Item = T>>(iter: I) -> Self {
+        let mut deque = fourth::Deque::new();
+        for elem in iter {
+            deque.push_back(elem);
+        }
+        deque
+    }
+}
+
+impl<T> FromFront<T> for fifth::UnsafeQueue<T> {
+    fn from_front<I: Iterator<Item = T>>(iter: I) -> Self {
+        let mut queue = fifth::UnsafeQueue::new();
+        for elem in iter {
+            queue.push(elem);
+        }
+        queue
+    }
+}
+
+impl<T> FromFront<T> for sixth::LinkedList<T> {
+    fn from_front<I: Iterator<Item = T>>(iter: I) -> Self {
+        iter.collect()
+    }
+}
+
+impl<T> FromFront<T> for Stack<T> {
+    fn from_front<I: Iterator<Item = T>>(iter: I) -> Self {
+        let mut stack = Stack::new();
+        for elem in iter {
+            stack.push(elem);
+        }
+        stack.reverse();
+        stack
+    }
+}
+
+impl<T> FromFront<T> for Vec<T> {
+    fn from_front<I: Iterator<Item = T>>(iter: I) -> Self {
+        iter.collect()
+    }
+}
+
+impl<T> FromFront<T> for VecDeque<T> {
+    fn from_front<I: Iterator<Item = T>>(iter: I) -> Self {
+        iter.collect()
+    }
+}
+
+impl<T> FromFront<T> for StdLinkedList<T> {
+    fn from_front<I: Iterator<Item = T>>(iter: I) -> Self {
+        iter.collect()
+    }
+}
+
+// `From` impls that move one element at a time, reading the source through its
+// (front first) `IntoIterator`.
+macro_rules! convert {
+    ($($from:ty),* => $to:ty) => {
+        $(
+            impl<T> From<$from> for $to {
+                fn from(list: $from) -> Self {
+                    FromFront::from_front(list.into_iter())
+                }
+            }
+        )*
+    };
+}
+
+convert!(
+    second::List<T>, fourth::Deque<T>, fifth::UnsafeQueue<T>, sixth::LinkedList<T>, Stack<T>,
+    Vec<T>, VecDeque<T>, StdLinkedList<T> => first::List<T>
+);
+convert!(
+    first::List<T>, fourth::Deque<T>, sixth::LinkedList<T>,
+    Vec<T>, VecDeque<T>, StdLinkedList<T> => second::List<T>
+);
+convert!(
+    first::List<T>, second::List<T>, fourth::Deque<T>, fifth::UnsafeQueue<T>,
+    sixth::LinkedList<T>, Stack<T>, Vec<T>, VecDeque<T>, StdLinkedList<T> => third::List<T>
+);
+convert!(
+    first::List<T>, second::List<T>, fifth::UnsafeQueue<T>, sixth::LinkedList<T>, Stack<T>,
+    Vec<T>, VecDeque<T>, StdLinkedList<T> => fourth::Deque<T>
+);
+convert!(
+    first::List<T>, fourth::Deque<T>, sixth::LinkedList<T>,
+    Vec<T>, VecDeque<T>, StdLinkedList<T> => fifth::UnsafeQueue<T>
+);
+convert!(
+    first::List<T>, second::List<T>, fourth::Deque<T>, fifth::UnsafeQueue<T>, Stack<T>,
+    Vec<T>, VecDeque<T>, StdLinkedList<T> => sixth::LinkedList<T>
+);
+convert!(
+    first::List<T>, fourth::Deque<T>, sixth::LinkedList<T>,
+    Vec<T>, VecDeque<T>, StdLinkedList<T> => Stack<T>
+);
+convert!(
+    first::List<T>, second::List<T>, fourth::Deque<T>, fifth::UnsafeQueue<T>,
+    sixth::LinkedList<T>, Stack<T> => Vec<T>
+);
+convert!(
+    first::List<T>, second::List<T>, fourth::Deque<T>, fifth::UnsafeQueue<T>,
+    sixth::LinkedList<T>, Stack<T> => VecDeque<T>
+);
+convert!(
+    first::List<T>, second::List<T>, fourth::Deque<T>, fifth::UnsafeQueue<T>,
+    sixth::LinkedList<T>, Stack<T> => StdLinkedList<T>
+);
+
+// The node-reusing conversions. All three node types are `#[repr(C)]` with an
+// `elem: T` followed by a nullable owning pointer to the next node, so a chain
+// built by one of them is a valid chain for the others.
+
+impl<T> From<second::List<T>> for Stack<T> {
+    fn from(list: second::List<T>) -> Self {
+        let (head, len) = list.into_raw();
+        unsafe { Stack::from_raw(head.cast(), len) }
+    }
+}
+
+impl<T> From<Stack<T>> for second::List<T> {
+    fn from(stack: Stack<T>) -> Self {
+        let (head, len) = stack.into_raw();
+        unsafe { second::List::from_raw(head.cast(), len) }
+    }
+}
+
+impl<T> From<second::List<T>> for fifth::UnsafeQueue<T> {
+    fn from(list: second::List<T>) -> Self {
+        let (head, _) = list.into_raw();
+        unsafe { fifth::UnsafeQueue::from_raw(head.cast()) }
+    }
+}
+
+impl<T> From<fifth::UnsafeQueue<T>> for second::List<T> {
+    fn from(queue: fifth::UnsafeQueue<T>) -> Self {
+        let (head, len) = queue.into_raw();
+        unsafe { second::List::from_raw(head.cast(), len) }
+    }
+}
+
+impl<T> From<Stack<T>> for fifth::UnsafeQueue<T> {
+    fn from(stack: Stack<T>) -> Self {
+        let (head, _) = stack.into_raw();
+        unsafe { fifth::UnsafeQueue::from_raw(head.cast()) }
+    }
+}
+
+impl<T> From<fifth::UnsafeQueue<T>> for Stack<T> {
+    fn from(queue: fifth::UnsafeQueue<T>) -> Self {
+        let (head, len) = queue.into_raw();
+        unsafe { Stack::from_raw(head.cast(), len) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{LinkedList as StdLinkedList, VecDeque};
+
+    use crate::double::stack::Stack;
+    use crate::{fifth, first, fourth, second, sixth, third};
+
+    #[test]
+    fn order() {
+        let vec = vec![1, 2, 3];
+
+        let first = first::List::from(vec.clone());
+        assert_eq!(first.peek(), Some(&1));
+        let second = second::List::from(first);
+        assert_eq!(second.peek(), Some(&1));
+        let mut deque = fourth::Deque::from(second);
+        assert_eq!(deque.pop_back(), Some(3));
+        deque.push_back(3);
+        let queue = fifth::UnsafeQueue::from(deque);
+        assert_eq!(queue.peak(), Some(&1));
+        let linked = sixth::LinkedList::from(queue);
+        assert_eq!(linked.front(), Some(&1));
+        assert_eq!(linked.back(), Some(&3));
+        let stack = Stack::from(linked);
+        assert_eq!(stack.peek(), Some(&1));
+
+        assert_eq!(Vec::from(stack), vec);
+
+        let persistent = third::List::from(vec.clone());
+        assert_eq!(persistent.iter().copied().collect::<Vec<_>>(), vec);
+    }
+
+    #[test]
+    fn from_agrees_with_collect() {
+        let vec = vec![1, 2, 3];
+
+        let converted = first::List::from(vec.clone());
+        let collected: first::List<i32> = vec.iter().copied().collect();
+        assert_eq!(converted, collected);
+
+        let mut extended = first::List::new();
+        extended.extend([1]);
+        extended.extend([2, 3]);
+        assert_eq!(converted, extended);
+        assert_eq!(converted.peek(), Some(&1));
+    }
+
+    #[test]
+    fn std_collections() {
+        let deque = VecDeque::from(vec![1, 2, 3]);
+        let list = sixth::LinkedList::from(deque);
+        let std_list = StdLinkedList::from(list);
+        assert_eq!(std_list.front(), Some(&1));
+
+        let mut stack = Stack::from(std_list);
+        assert_eq!(stack.pop(), Some(1));
+        let deque = VecDeque::from(stack);
+        assert_eq!(deque, &[2, 3]);
+
+        let mut list = second::List::new();
+        list.push(1);
+        list.push(2);
+        // The most recently pushed element comes first
+        assert_eq!(Vec::from(list), &[2, 1]);
+    }
+
+    #[test]
+    fn reuses_nodes() {
+        let mut list = second::List::new();
+        for i in (0..4).rev() {
+            list.push(i.to_string());
+        }
+        let addresses = |iter: &mut dyn Iterator<Item = &String>| {
+            iter.map(|s| s as *const String).collect::<Vec<_>>()
+        };
+        let before = addresses(&mut list.iter());
+
+        let stack = Stack::from(list);
+        assert_eq!(stack.len(), 4);
+        assert_eq!(addresses(&mut stack.iter()), before);
+
+        let mut queue = fifth::UnsafeQueue::from(stack);
+        assert_eq!(addresses(&mut queue.iter()), before);
+        // The tail has to be right for pushes to land at the end
+        queue.push("4".to_string());
+        assert_eq!(queue.pop().as_deref(), Some("0"));
+
+        let list = second::List::from(queue);
+        assert_eq!(list.len(), 4);
+        let after = addresses(&mut list.iter());
+        assert_eq!(after[..3], before[1..]);
+
+        let queue = fifth::UnsafeQueue::from(list);
+        let stack = Stack::from(queue);
+        let list = second::List::from(stack);
+        assert_eq!(addresses(&mut list.iter()), after);
+        assert_eq!(Vec::from(list), &["1", "2", "3", "4"]);
+    }
+
+    #[test]
+    fn empty() {
+        let queue = fifth::UnsafeQueue::from(second::List::<i32>::new());
+        let stack = Stack::from(queue);
+        assert!(stack.is_empty());
+        let mut queue = fifth::UnsafeQueue::from(stack);
+        queue.push(1);
+        assert_eq!(second::List::from(queue).peek(), Some(&1));
+    }
+}
@@ -0,0 +1,194 @@
+// Undo/redo history on top of the left/right stacks of `double::List`.
+//
+// The left stack holds the current state on top with older states below it,
+// the right stack holds the states we can redo into. Undo and redo just move
+// nodes between the two stacks, so no state is ever reallocated.
+use crate::double::stack::Iter;
+use crate::double::List;
+
+pub struct History<T> {
+    list: List<T>,
+    capacity: Option<usize>,
+}
+
+impl<T> History<T> {
+    pub fn new() -> Self {
+        History {
+            list: List::new(),
+            capacity: None,
+        }
+    }
+
+    // Keeps at most `capacity` states on the undo side (current included),
+    // forgetting the oldest ones first.
+    pub fn with_capacity(capacity: usize) -> Self {
+        assert!(capacity > 0, "history capacity must be non-zero");
+        History {
+            capacity: Some(capacity),
+            ..Self::new()
+        }
+    }
+
+    // Makes `state` the current state. Anything that could be redone is lost.
+    pub fn record(&mut self, state: T) {
+        self.list.right.clear();
+        self.list.push_left(state);
+
+        if let Some(capacity) = self.capacity {
+            self.list.left.truncate(capacity);
+        }
+    }
+
+    // Steps back to the previous state and returns it.
+    pub fn undo(&mut self) -> Option<&T> {
+        if !self.can_undo() {
+            return None;
+        }
+        self.list.go_left();
+        self.current()
+    }
+
+    // Steps forward to the next state and returns it.
+    pub fn redo(&mut self) -> Option<&T> {
+        if !self.list.go_right() {
+            return None;
+        }
+        self.current()
+    }
+
+    pub fn current(&self) -> Option<&T> {
+        self.list.peek_left()
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut T> {
+        self.list.peek_left_mut()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.list.left.len() > 1
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.list.right.is_empty()
+    }
+
+    // States `undo` would walk back through, most recent first.
+    pub fn past(&self) -> std::iter::Skip<Iter<'_, T>> {
+        self.list.left.iter().skip(1)
+    }
+
+    // States `redo` would walk forward through, next first.
+    pub fn future(&self) -> Iter<'_, T> {
+        self.list.right.iter()
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.left.len() + self.list.right.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.list.left.clear();
+        self.list.right.clear();
+    }
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::History;
+
+    #[test]
+    fn undo_redo() {
+        let mut history = History::new();
+        assert_eq!(history.current(), None);
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.redo(), None);
+
+        history.record(1);
+        history.record(2);
+        history.record(3);
+        assert_eq!(history.current(), Some(&3));
+
+        assert_eq!(history.undo(), Some(&2));
+        assert_eq!(history.undo(), Some(&1));
+        // Can't undo past the first state
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.current(), Some(&1));
+
+        assert_eq!(history.redo(), Some(&2));
+        assert_eq!(history.redo(), Some(&3));
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.len(), 3);
+    }
+
+    #[test]
+    fn record_truncates_redo() {
+        let mut history = History::new();
+        history.record("a");
+        history.record("b");
+        history.record("c");
+        history.undo();
+        history.undo();
+        assert!(history.can_redo());
+
+        history.record("d");
+        assert!(!history.can_redo());
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.past().copied().collect::<Vec<_>>(), &["a"]);
+        assert_eq!(history.current(), Some(&"d"));
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn capacity() {
+        let mut history = History::with_capacity(3);
+        for state in 0..10 {
+            history.record(state);
+        }
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.current(), Some(&9));
+        assert_eq!(history.undo(), Some(&8));
+        assert_eq!(history.undo(), Some(&7));
+        assert_eq!(history.undo(), None);
+
+        // Recording again throws away the redo branch
+        history.record(42);
+        assert_eq!(history.past().copied().collect::<Vec<_>>(), &[7]);
+    }
+
+    #[test]
+    fn past_and_future() {
+        let mut history = History::new();
+        history.record(1);
+        history.record(2);
+        history.record(3);
+        history.record(4);
+        history.undo();
+        history.undo();
+
+        assert_eq!(history.current(), Some(&2));
+        assert_eq!(history.past().copied().collect::<Vec<_>>(), &[1]);
+        assert_eq!(history.future().copied().collect::<Vec<_>>(), &[3, 4]);
+
+        *history.current_mut().unwrap() = 20;
+        assert_eq!(history.redo(), Some(&3));
+        assert_eq!(history.past().copied().collect::<Vec<_>>(), &[20, 1]);
+
+        history.clear();
+        assert!(history.is_empty());
+        assert_eq!(history.current(), None);
+    }
+}
@@ -1,6 +1,9 @@
 use crate::double::stack::Stack;
 
-mod stack;
+pub use history::History;
+
+mod history;
+pub mod stack;
 
 pub struct List<T> {
     left: Stack<T>,
@@ -8,7 +11,7 @@ pub struct List<T> {
 }
 
 impl<T> List<T> {
-    fn new() -> Self {
+    pub fn new() -> Self {
         List {
             left: Stack::new(),
             right: Stack::new(),
@@ -58,6 +61,12 @@ impl<T> List<T> {
     }
 }
 
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::List;
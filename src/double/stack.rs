@@ -1,24 +1,37 @@
 pub struct Stack<T> {
     head: Link<T>,
+    len: usize,
 }
 
 type Link<T> = Option<Box<Node<T>>>;
 
+// A node can't be built from outside this module: the only way to get one is
+// `pop_node`, and the only thing to do with it is `push_node` it somewhere.
+// It's `#[repr(C)]` so `convert` can hand whole chains to the other singly
+// linked lists in the crate.
+#[repr(C)]
 pub struct Node<T> {
     elem: T,
     next: Link<T>,
 }
 
+impl<T> Node<T> {
+    pub fn elem(&self) -> &T {
+        &self.elem
+    }
+
+    pub fn elem_mut(&mut self) -> &mut T {
+        &mut self.elem
+    }
+}
+
 impl<T> Stack<T> {
     pub fn new() -> Self {
-        Stack { head: None }
+        Stack { head: None, len: 0 }
     }
 
     pub fn push(&mut self, elem: T) {
-        let new_node = Box::new(Node {
-            elem: elem,
-            next: None,
-        });
+        let new_node = Box::new(Node { elem, next: None });
 
         self.push_node(new_node);
     }
@@ -26,6 +39,7 @@ impl<T> Stack<T> {
     pub fn push_node(&mut self, mut node: Box<Node<T>>) {
         node.next = self.head.take();
         self.head = Some(node);
+        self.len += 1;
     }
 
     pub fn pop(&mut self) -> Option<T> {
@@ -35,6 +49,7 @@ impl<T> Stack<T> {
     pub fn pop_node(&mut self) -> Option<Box<Node<T>>> {
         self.head.take().map(|mut node| {
             self.head = node.next.take();
+            self.len -= 1;
             node
         })
     }
@@ -46,6 +61,94 @@ impl<T> Stack<T> {
     pub fn peek_mut(&mut self) -> Option<&mut T> {
         self.head.as_mut().map(|node| &mut node.elem)
     }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Keeps the top `len` elements and drops everything below them.
+    pub fn truncate(&mut self, len: usize) {
+        let mut link = &mut self.head;
+        for _ in 0..len {
+            match link {
+                Some(node) => link = &mut node.next,
+                None => return,
+            }
+        }
+        let mut cur_link = link.take();
+        while let Some(mut boxed_node) = cur_link {
+            cur_link = boxed_node.next.take();
+        }
+        self.len = self.len.min(len);
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0)
+    }
+
+    // Flips the stack in place by relinking the existing nodes.
+    pub fn reverse(&mut self) {
+        let mut reversed = None;
+        let mut cur_link = self.head.take();
+        while let Some(mut boxed_node) = cur_link {
+            cur_link = boxed_node.next.take();
+            boxed_node.next = reversed;
+            reversed = Some(boxed_node);
+        }
+        self.head = reversed;
+    }
+
+    // Pops up to `n` nodes off this stack and pushes them onto `other`, one at
+    // a time, so they land on `other` in reverse order. No node is reallocated.
+    // Returns how many nodes were actually moved.
+    pub fn transfer_n_to(&mut self, other: &mut Stack<T>, n: usize) -> usize {
+        let mut moved = 0;
+        while moved < n {
+            match self.pop_node() {
+                Some(node) => other.push_node(node),
+                None => break,
+            }
+            moved += 1;
+        }
+        moved
+    }
+
+    // Gives up the node chain, for `convert`.
+    pub(crate) fn into_raw(mut self) -> (*mut Node<T>, usize) {
+        let head = self.head.take().map_or(std::ptr::null_mut(), Box::into_raw);
+        (head, self.len)
+    }
+
+    // Takes over a node chain, for `convert`.
+    //
+    // SAFETY: `head` must be null or the start of a chain of exactly `len`
+    // boxed nodes that nothing else owns.
+    pub(crate) unsafe fn from_raw(head: *mut Node<T>, len: usize) -> Self {
+        let head = if head.is_null() {
+            None
+        } else {
+            Some(Box::from_raw(head))
+        };
+        Stack { head, len }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            next: self.head.as_deref(),
+            len: self.len,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            next: self.head.as_deref_mut(),
+            len: self.len,
+        }
+    }
 }
 
 impl<T> Drop for Stack<T> {
@@ -56,3 +159,191 @@ impl<T> Drop for Stack<T> {
         }
     }
 }
+
+impl<T> Default for Stack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct IntoIter<T>(Stack<T>);
+
+pub struct Iter<'a, T> {
+    next: Option<&'a Node<T>>,
+    len: usize,
+}
+
+pub struct IterMut<'a, T> {
+    next: Option<&'a mut Node<T>>,
+    len: usize,
+}
+
+impl<T> IntoIterator for Stack<T> {
+    type IntoIter = IntoIter<T>;
+    type Item = T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Stack<T> {
+    type IntoIter = Iter<'a, T>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut Stack<T> {
+    type IntoIter = IterMut<'a, T>;
+    type Item = &'a mut T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.map(|node| {
+            self.next = node.next.as_deref();
+            self.len -= 1;
+            &node.elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.take().map(|node| {
+            self.next = node.next.as_deref_mut();
+            self.len -= 1;
+            &mut node.elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {}
+
+#[cfg(test)]
+mod test {
+    use super::Stack;
+
+    fn stack_from(v: &[i32]) -> Stack<i32> {
+        let mut stack = Stack::new();
+        for &x in v {
+            stack.push(x);
+        }
+        stack
+    }
+
+    #[test]
+    fn iterators() {
+        let mut stack = stack_from(&[1, 2, 3]);
+        assert_eq!(stack.len(), 3);
+
+        let mut iter = stack.iter();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next(), Some(&3));
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next(), None);
+
+        for elem in &mut stack {
+            *elem *= 10;
+        }
+        assert_eq!(stack.peek(), Some(&30));
+
+        let mut iter = stack.into_iter();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next(), Some(30));
+        assert_eq!(iter.next(), Some(20));
+        assert_eq!(iter.next(), Some(10));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn reverse() {
+        let mut stack = stack_from(&[]);
+        stack.reverse();
+        assert!(stack.is_empty());
+
+        let mut stack = stack_from(&[1, 2, 3, 4]);
+        let top = stack.peek().unwrap() as *const i32;
+        stack.reverse();
+        assert_eq!(stack.iter().copied().collect::<Vec<_>>(), &[1, 2, 3, 4]);
+        assert_eq!(stack.len(), 4);
+        // The old top is still the same allocation, just at the bottom now
+        assert_eq!(stack.iter().last().unwrap() as *const i32, top);
+    }
+
+    #[test]
+    fn transfer() {
+        let mut from = stack_from(&[1, 2, 3, 4]);
+        let mut to = stack_from(&[10]);
+        let top = from.peek().unwrap() as *const i32;
+
+        assert_eq!(from.transfer_n_to(&mut to, 2), 2);
+        assert_eq!(from.iter().copied().collect::<Vec<_>>(), &[2, 1]);
+        assert_eq!(to.iter().copied().collect::<Vec<_>>(), &[3, 4, 10]);
+        assert_eq!(to.iter().nth(1).unwrap() as *const i32, top);
+        assert_eq!((from.len(), to.len()), (2, 3));
+
+        // Asking for more than there is moves what's left
+        assert_eq!(from.transfer_n_to(&mut to, 10), 2);
+        assert!(from.is_empty());
+        assert_eq!(to.len(), 5);
+        assert_eq!(from.transfer_n_to(&mut to, 1), 0);
+    }
+
+    #[test]
+    fn nodes() {
+        let mut a = stack_from(&[1, 2]);
+        let mut b = Stack::new();
+
+        let mut node = a.pop_node().unwrap();
+        assert_eq!(node.elem(), &2);
+        *node.elem_mut() = 20;
+        b.push_node(node);
+
+        assert_eq!((a.len(), b.len()), (1, 1));
+        assert_eq!(b.pop(), Some(20));
+
+        a.truncate(5);
+        assert_eq!(a.len(), 1);
+        a.truncate(0);
+        assert_eq!(a.len(), 0);
+        assert_eq!(a.pop_node().map(|node| *node.elem()), None);
+    }
+}
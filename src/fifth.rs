@@ -1,11 +1,22 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr;
+
 pub struct UnsafeQueue<T> {
     head: Link<T>,
     tail: *mut Node<T>,
+    len: usize,
 }
 
 type Link<T> = *mut Node<T>;
 
-struct Node<T> {
+// `#[repr(C)]` so this has the same layout as the other singly linked nodes
+// in the crate, which lets `convert` hand whole chains between lists.
+#[repr(C)]
+pub(crate) struct Node<T> {
     elem: T,
     next: Link<T>,
 }
@@ -13,8 +24,9 @@ struct Node<T> {
 impl<T> UnsafeQueue<T> {
     pub fn new() -> Self {
         UnsafeQueue {
-            head: 0 as *mut _,
-            tail: 0 as *mut _,
+            head: ptr::null_mut(),
+            tail: ptr::null_mut(),
+            len: 0,
         }
         // lmfao what the frickery did I just do
         // did I just cast 0 to a mutable pointer?
@@ -26,7 +38,7 @@ impl<T> UnsafeQueue<T> {
         // Put the box in the right place, and then grab a reference to its Node
         let raw_tail: *mut _ = Box::into_raw(Box::new(Node {
             elem,
-            next: 0 as *mut _, // this is the new last element of the queue
+            next: ptr::null_mut(), // this is the new last element of the queue
         }));
 
         if self.tail.is_null() {
@@ -37,7 +49,8 @@ impl<T> UnsafeQueue<T> {
             }
         }
 
-        self.tail = raw_tail
+        self.tail = raw_tail;
+        self.len += 1;
     }
     pub fn pop(&mut self) -> Option<T> {
         unsafe {
@@ -48,18 +61,103 @@ impl<T> UnsafeQueue<T> {
                 self.head = head.next;
 
                 if self.head.is_null() {
-                    self.tail = std::ptr::null_mut();
+                    self.tail = ptr::null_mut();
                 }
+                self.len -= 1;
                 Some(head.elem)
             }
         }
     }
+    // Gives up the node chain, for `convert`.
+    pub(crate) fn into_raw(mut self) -> (*mut Node<T>, usize) {
+        let head = mem::replace(&mut self.head, ptr::null_mut());
+        self.tail = ptr::null_mut();
+        (head, mem::take(&mut self.len))
+    }
+
+    // Takes over a node chain, for `convert`.
+    //
+    // SAFETY: `head` must be null or the start of a null-terminated chain of
+    // boxed nodes that nothing else owns.
+    pub(crate) unsafe fn from_raw(head: *mut Node<T>) -> Self {
+        let mut tail = head;
+        let mut len = if head.is_null() { 0 } else { 1 };
+        while !tail.is_null() && !(*tail).next.is_null() {
+            tail = (*tail).next;
+            len += 1;
+        }
+        UnsafeQueue { head, tail, len }
+    }
+
     pub fn peak(&self) -> Option<&T> {
         unsafe { self.head.as_ref().map(|node| &node.elem) }
     }
-    pub fn peak_mut(&self) -> Option<&mut T> {
+    pub fn peak_mut(&mut self) -> Option<&mut T> {
         unsafe { self.head.as_mut().map(|node| &mut node.elem) }
     }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn clear(&mut self) {
+        while self.pop().is_some() {}
+    }
+    // Moves every element of `other` onto the back of this queue in O(1),
+    // leaving `other` empty.
+    pub fn append(&mut self, other: &mut Self) {
+        if other.is_empty() {
+            return;
+        }
+        if self.tail.is_null() {
+            mem::swap(self, other);
+            return;
+        }
+        unsafe {
+            (*self.tail).next = mem::replace(&mut other.head, ptr::null_mut());
+        }
+        self.tail = mem::replace(&mut other.tail, ptr::null_mut());
+        self.len += mem::take(&mut other.len);
+    }
+    // Keeps the first `at` elements and returns the rest as a new queue.
+    // Panics if `at > len`.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        assert!(
+            at <= self.len,
+            "split index (is {}) should be <= len (is {})",
+            at,
+            self.len
+        );
+        if at == 0 {
+            return mem::take(self);
+        }
+        unsafe {
+            // Find the node that becomes our new tail
+            let mut new_tail = self.head;
+            for _ in 1..at {
+                new_tail = (*new_tail).next;
+            }
+            let rest_head = mem::replace(&mut (*new_tail).next, ptr::null_mut());
+            let rest = UnsafeQueue {
+                head: rest_head,
+                tail: if rest_head.is_null() {
+                    ptr::null_mut()
+                } else {
+                    self.tail
+                },
+                len: self.len - at,
+            };
+            self.tail = new_tail;
+            self.len = at;
+            rest
+        }
+    }
+    // Pops elements from the front as it's iterated. Whatever is left when
+    // the `Drain` is dropped gets dropped too, so the queue always ends up empty.
+    pub fn drain(&mut self) -> Drain<'_, T> {
+        Drain { queue: self }
+    }
 }
 impl<T> Drop for UnsafeQueue<T> {
     fn drop(&mut self) {
@@ -71,35 +169,117 @@ impl<T> Default for UnsafeQueue<T> {
         Self::new()
     }
 }
+impl<T: Clone> Clone for UnsafeQueue<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+impl<T> Extend<T> for UnsafeQueue<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+impl<T> FromIterator<T> for UnsafeQueue<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut queue = Self::new();
+        queue.extend(iter);
+        queue
+    }
+}
+impl<T: fmt::Debug> fmt::Debug for UnsafeQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+impl<T: PartialEq> PartialEq for UnsafeQueue<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other)
+    }
+}
+impl<T: Eq> Eq for UnsafeQueue<T> {}
+impl<T: PartialOrd> PartialOrd for UnsafeQueue<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other)
+    }
+}
+impl<T: Ord> Ord for UnsafeQueue<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other)
+    }
+}
+impl<T: Hash> Hash for UnsafeQueue<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        for item in self {
+            item.hash(state);
+        }
+    }
+}
+
+unsafe impl<T: Send> Send for UnsafeQueue<T> {}
+unsafe impl<T: Sync> Sync for UnsafeQueue<T> {}
 
 pub struct IntoIter<T>(UnsafeQueue<T>);
 
+pub struct Drain<'a, T> {
+    queue: &'a mut UnsafeQueue<T>,
+}
+
 pub struct Iter<'a, T> {
-    next: Option<&'a Node<T>>,
+    next: Link<T>,
+    len: usize,
+    _boo: PhantomData<&'a T>,
 }
 
 pub struct IterMut<'a, T> {
-    next: Option<&'a mut Node<T>>,
+    next: Link<T>,
+    len: usize,
+    _boo: PhantomData<&'a mut T>,
 }
 
-impl<T> UnsafeQueue<T> {
-    pub fn into_iter(self) -> IntoIter<T> {
+impl<T> IntoIterator for UnsafeQueue<T> {
+    type IntoIter = IntoIter<T>;
+    type Item = T;
+
+    fn into_iter(self) -> Self::IntoIter {
         IntoIter(self)
     }
+}
+
+impl<'a, T> IntoIterator for &'a UnsafeQueue<T> {
+    type IntoIter = Iter<'a, T>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut UnsafeQueue<T> {
+    type IntoIter = IterMut<'a, T>;
+    type Item = &'a mut T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
 
+impl<T> UnsafeQueue<T> {
     pub fn iter(&self) -> Iter<'_, T> {
-        unsafe {
-            Iter {
-                next: self.head.as_ref(),
-            }
+        Iter {
+            next: self.head,
+            len: self.len,
+            _boo: PhantomData,
         }
     }
 
     pub fn iter_mut(&mut self) -> IterMut<'_, T> {
-        unsafe {
-            IterMut {
-                next: self.head.as_mut(),
-            }
+        IterMut {
+            next: self.head,
+            len: self.len,
+            _boo: PhantomData,
         }
     }
 }
@@ -109,6 +289,16 @@ impl<T> Iterator for IntoIter<T> {
     fn next(&mut self) -> Option<Self::Item> {
         self.0.pop()
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {
+    fn len(&self) -> usize {
+        self.0.len
+    }
 }
 
 impl<'a, T> Iterator for Iter<'a, T> {
@@ -116,30 +306,96 @@ impl<'a, T> Iterator for Iter<'a, T> {
 
     fn next(&mut self) -> Option<Self::Item> {
         unsafe {
-            self.next.map(|node| {
-                self.next = node.next.as_ref();
+            self.next.as_ref().map(|node| {
+                self.len -= 1;
+                self.next = node.next;
                 &node.elem
             })
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {
+    fn len(&self) -> usize {
+        self.len
+    }
 }
 
 impl<'a, T> Iterator for IterMut<'a, T> {
     type Item = &'a mut T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.next.take().map(|node| {
-            unsafe {
-                self.next = node.next.as_mut();
-            }
-            &mut node.elem
-        })
+        unsafe {
+            self.next.as_mut().map(|node| {
+                self.len -= 1;
+                self.next = node.next;
+                &mut node.elem
+            })
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<'a, T> Iterator for Drain<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.queue.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.queue.len, Some(self.queue.len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Drain<'a, T> {
+    fn len(&self) -> usize {
+        self.queue.len
     }
 }
 
+impl<'a, T> Drop for Drain<'a, T> {
+    fn drop(&mut self) {
+        self.queue.clear();
+    }
+}
+
+unsafe impl<'a, T: Sync> Send for Iter<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for Iter<'a, T> {}
+
+unsafe impl<'a, T: Send> Send for IterMut<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for IterMut<'a, T> {}
+
 #[cfg(test)]
 mod test {
     use super::UnsafeQueue;
+
+    // Walks the queue and checks `head`, `tail` and `len` all agree.
+    fn check_links<T>(queue: &UnsafeQueue<T>) {
+        assert_eq!(queue.head.is_null(), queue.tail.is_null());
+        let mut count = 0;
+        let mut last = std::ptr::null_mut();
+        let mut cur = queue.head;
+        while !cur.is_null() {
+            count += 1;
+            last = cur;
+            cur = unsafe { (*cur).next };
+        }
+        assert_eq!(count, queue.len);
+        assert_eq!(last, queue.tail);
+    }
     #[test]
     fn basics() {
         let mut list = UnsafeQueue::new();
@@ -177,4 +433,169 @@ mod test {
         assert_eq!(list.pop(), Some(7));
         assert_eq!(list.pop(), None);
     }
+
+    #[test]
+    fn len() {
+        let mut list = UnsafeQueue::new();
+        assert_eq!(list.len(), 0);
+        assert!(list.is_empty());
+        list.push(1);
+        list.push(2);
+        assert_eq!(list.len(), 2);
+        list.pop();
+        list.pop();
+        list.pop();
+        assert_eq!(list.len(), 0);
+    }
+
+    #[test]
+    fn iterators() {
+        let mut list: UnsafeQueue<i32> = (1..=3).collect();
+
+        let mut iter = list.iter();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.size_hint(), (2, Some(2)));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next(), Some(&3));
+        assert_eq!(iter.len(), 0);
+        assert_eq!(iter.next(), None);
+
+        for elem in &mut list {
+            *elem *= 10;
+        }
+        let mut iter = list.iter_mut();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next(), Some(&mut 10));
+        assert_eq!(iter.len(), 2);
+
+        let mut sum = 0;
+        for elem in &list {
+            sum += elem;
+        }
+        assert_eq!(sum, 60);
+
+        let mut iter = list.into_iter();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next(), Some(10));
+        assert_eq!(iter.len(), 2);
+    }
+
+    #[test]
+    fn traits() {
+        let mut list: UnsafeQueue<i32> = (1..=3).collect();
+        list.extend([4, 5]);
+        assert_eq!(format!("{:?}", list), "[1, 2, 3, 4, 5]");
+
+        let mut cloned = list.clone();
+        assert_eq!(list, cloned);
+        assert_eq!(cloned.len(), 5);
+        cloned.push(6);
+        assert_ne!(list, cloned);
+        assert!(list < cloned);
+        cloned.pop();
+        assert!(list < cloned);
+        assert_eq!(list.cmp(&list.clone()), std::cmp::Ordering::Equal);
+
+        let mut map = std::collections::HashMap::new();
+        map.insert(list.clone(), "list");
+        assert_eq!(map.get(&list), Some(&"list"));
+        assert_eq!(map.get(&cloned), None);
+    }
+
+    #[test]
+    fn pop_resets_tail() {
+        let mut list = UnsafeQueue::new();
+        list.push(1);
+        check_links(&list);
+        assert_eq!(list.pop(), Some(1));
+        // Popping the last element has to null out the tail too
+        assert!(list.tail.is_null());
+        check_links(&list);
+
+        list.push(2);
+        check_links(&list);
+        assert_eq!(list.peak(), Some(&2));
+    }
+
+    #[test]
+    fn append() {
+        let mut a: UnsafeQueue<i32> = (1..=3).collect();
+        let mut b: UnsafeQueue<i32> = (4..=5).collect();
+        let b_tail = b.tail;
+
+        a.append(&mut b);
+        check_links(&a);
+        check_links(&b);
+        assert_eq!(a.tail, b_tail);
+        assert!(b.is_empty());
+        assert_eq!(a.iter().copied().collect::<Vec<_>>(), &[1, 2, 3, 4, 5]);
+
+        // Appending an empty queue does nothing, appending to one moves everything
+        a.append(&mut b);
+        check_links(&a);
+        b.append(&mut a);
+        check_links(&a);
+        check_links(&b);
+        assert_eq!(b.len(), 5);
+
+        // The tail is still right for new pushes
+        b.push(6);
+        check_links(&b);
+        assert_eq!(b.into_iter().last(), Some(6));
+    }
+
+    #[test]
+    fn split_off() {
+        let mut list: UnsafeQueue<i32> = (0..5).collect();
+
+        let rest = list.split_off(2);
+        check_links(&list);
+        check_links(&rest);
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), &[0, 1]);
+        assert_eq!(rest.iter().copied().collect::<Vec<_>>(), &[2, 3, 4]);
+
+        let empty = list.split_off(2);
+        check_links(&list);
+        check_links(&empty);
+        assert!(empty.is_empty());
+
+        let all = list.split_off(0);
+        check_links(&list);
+        check_links(&all);
+        assert!(list.is_empty());
+        assert_eq!(all.len(), 2);
+
+        list.push(7);
+        check_links(&list);
+    }
+
+    #[test]
+    #[should_panic]
+    fn split_off_out_of_bounds() {
+        let mut list: UnsafeQueue<i32> = (0..2).collect();
+        list.split_off(3);
+    }
+
+    #[test]
+    fn drain_and_clear() {
+        let mut list: UnsafeQueue<i32> = (0..5).collect();
+        let mut drain = list.drain();
+        assert_eq!(drain.len(), 5);
+        assert_eq!(drain.next(), Some(0));
+        assert_eq!(drain.next(), Some(1));
+        drop(drain);
+        // Dropping the drain early still empties the queue
+        assert!(list.is_empty());
+        check_links(&list);
+
+        list.extend([1, 2]);
+        assert_eq!(list.drain().collect::<Vec<_>>(), &[1, 2]);
+        check_links(&list);
+
+        list.extend([1, 2]);
+        list.clear();
+        check_links(&list);
+        assert_eq!(list.pop(), None);
+    }
 }
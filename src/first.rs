@@ -1,3 +1,4 @@
+use std::fmt;
 use std::mem;
 
 struct Node<T> {
@@ -9,14 +10,33 @@ enum Link<T> {
     Empty,
     More(Box<Node<T>>),
 }
+impl<T> Link<T> {
+    fn as_node(&self) -> Option<&Node<T>> {
+        match self {
+            Link::Empty => None,
+            Link::More(node) => Some(node),
+        }
+    }
+
+    fn as_node_mut(&mut self) -> Option<&mut Node<T>> {
+        match self {
+            Link::Empty => None,
+            Link::More(node) => Some(node),
+        }
+    }
+}
 
 pub struct List<T> {
     head: Link<T>,
+    len: usize,
 }
 impl<T> List<T> {
     // Returns an empty list.
     pub fn new() -> Self {
-        List { head: Link::Empty }
+        List {
+            head: Link::Empty,
+            len: 0,
+        }
     }
 
     // Pushes an element to the front of the list.
@@ -26,6 +46,7 @@ impl<T> List<T> {
             next: mem::replace(&mut self.head, Link::Empty),
         });
         self.head = Link::More(new_node);
+        self.len += 1;
     }
 
     // Pops the first element from the list.
@@ -35,11 +56,46 @@ impl<T> List<T> {
             Link::Empty => res = None,
             Link::More(node) => {
                 self.head = node.next;
+                self.len -= 1;
                 res = Some(node.elem);
             }
         }
         res
     }
+
+    // Returns a reference to the first element of the list.
+    pub fn peek(&self) -> Option<&T> {
+        self.head.as_node().map(|node| &node.elem)
+    }
+
+    // Returns a mutable reference to the first element of the list.
+    pub fn peek_mut(&mut self) -> Option<&mut T> {
+        self.head.as_node_mut().map(|node| &mut node.elem)
+    }
+
+    // Returns the number of elements in the list.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Iterates from the first (most recently pushed) element.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            next: self.head.as_node(),
+            len: self.len,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            next: self.head.as_node_mut(),
+            len: self.len,
+        }
+    }
 }
 impl<T> Drop for List<T> {
     fn drop(&mut self) {
@@ -54,6 +110,128 @@ impl<T> Default for List<T> {
         Self::new()
     }
 }
+impl<T: Clone> Clone for List<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+// Appends at the back in iteration order, so `collect` and `From<Vec<T>>`
+// both keep the first item at the front.
+impl<T> Extend<T> for List<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut tail = &mut self.head;
+        while let Link::More(node) = tail {
+            tail = &mut node.next;
+        }
+        for elem in iter {
+            *tail = Link::More(Box::new(Node {
+                elem,
+                next: Link::Empty,
+            }));
+            tail = match tail {
+                Link::More(node) => &mut node.next,
+                Link::Empty => unreachable!(),
+            };
+            self.len += 1;
+        }
+    }
+}
+impl<T> FromIterator<T> for List<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+impl<T: fmt::Debug> fmt::Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+impl<T: PartialEq> PartialEq for List<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other)
+    }
+}
+impl<T: Eq> Eq for List<T> {}
+
+pub struct IntoIter<T>(List<T>);
+
+pub struct Iter<'a, T> {
+    next: Option<&'a Node<T>>,
+    len: usize,
+}
+
+pub struct IterMut<'a, T> {
+    next: Option<&'a mut Node<T>>,
+    len: usize,
+}
+
+impl<T> IntoIterator for List<T> {
+    type IntoIter = IntoIter<T>;
+    type Item = T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+impl<'a, T> IntoIterator for &'a List<T> {
+    type IntoIter = Iter<'a, T>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+impl<'a, T> IntoIterator for &'a mut List<T> {
+    type IntoIter = IterMut<'a, T>;
+    type Item = &'a mut T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.map(|node| {
+            self.next = node.next.as_node();
+            self.len -= 1;
+            &node.elem
+        })
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.take().map(|node| {
+            self.next = node.next.as_node_mut();
+            self.len -= 1;
+            &mut node.elem
+        })
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {}
 #[cfg(test)]
 mod tests {
     use super::List;
@@ -110,4 +288,55 @@ mod tests {
         // Check none at the end
         assert_eq!(list.pop(), None);
     }
+
+    #[test]
+    fn first_list_peek() {
+        let mut list = List::new();
+        assert_eq!(list.peek(), None);
+        assert_eq!(list.peek_mut(), None);
+        list.push(1);
+        list.push(2);
+        assert_eq!(list.len(), 2);
+
+        assert_eq!(list.peek(), Some(&2));
+        *list.peek_mut().unwrap() = 42;
+        assert_eq!(list.pop(), Some(42));
+        assert_eq!(list.len(), 1);
+    }
+
+    #[test]
+    fn first_list_iter() {
+        let mut list: List<i32> = (1..=3).collect();
+        assert_eq!(list.len(), 3);
+
+        let mut iter = list.iter();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next(), Some(&3));
+        assert_eq!(iter.next(), None);
+
+        for elem in &mut list {
+            *elem *= 10;
+        }
+        list.extend([40, 50]);
+        assert_eq!(list.len(), 5);
+        assert_eq!(list.into_iter().collect::<Vec<_>>(), &[10, 20, 30, 40, 50]);
+    }
+
+    #[test]
+    fn first_list_traits() {
+        let list: List<i32> = (1..=3).collect();
+        let cloned = list.clone();
+        assert_eq!(list, cloned);
+        assert_eq!(format!("{:?}", cloned), "[1, 2, 3]");
+        assert_eq!(cloned.len(), 3);
+
+        let mut other = List::new();
+        other.push(3);
+        other.push(2);
+        assert_ne!(list, other);
+        other.push(1);
+        assert_eq!(list, other);
+    }
 }
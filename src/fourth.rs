@@ -60,8 +60,8 @@ impl<T> Deque<T> {
                                             // total: +2 new_tail, +0 old_tail -- OK!
             }
             None => {
-                // empty list, need to set the tail
-                self.tail = Some(new_tail.clone()); // +1 new_tail
+                // empty list, need to set the head
+                self.head = Some(new_tail.clone()); // +1 new_tail
                 self.tail = Some(new_tail); // +1 new_tail
                                             // total: +2 new_tail -- OK!
             }
@@ -95,29 +95,26 @@ impl<T> Deque<T> {
             Rc::try_unwrap(old_tail).ok().unwrap().into_inner().elem
         })
     }
-    pub fn peek_front(&self) -> Option<Ref<T>> {
+    pub fn peek_front(&self) -> Option<Ref<'_, T>> {
         self.head
             .as_ref()
             .map(|node| Ref::map(node.borrow(), |node| &node.elem))
     }
-    pub fn peek_back(&self) -> Option<Ref<T>> {
+    pub fn peek_back(&self) -> Option<Ref<'_, T>> {
         self.tail
             .as_ref()
             .map(|node| Ref::map(node.borrow(), |node| &node.elem))
     }
-    pub fn peek_back_mut(&mut self) -> Option<RefMut<T>> {
+    pub fn peek_back_mut(&mut self) -> Option<RefMut<'_, T>> {
         self.tail
             .as_ref()
             .map(|node| RefMut::map(node.borrow_mut(), |node| &mut node.elem))
     }
-    pub fn peek_front_mut(&mut self) -> Option<RefMut<T>> {
+    pub fn peek_front_mut(&mut self) -> Option<RefMut<'_, T>> {
         self.head
             .as_ref()
             .map(|node| RefMut::map(node.borrow_mut(), |node| &mut node.elem))
     }
-    pub fn into_iter(self) -> DequeIterator<T> {
-        DequeIterator(self)
-    }
 }
 impl<T> Drop for Deque<T> {
     fn drop(&mut self) {
@@ -132,6 +129,15 @@ impl<T> Default for Deque<T> {
 
 pub struct DequeIterator<T>(Deque<T>);
 
+impl<T> IntoIterator for Deque<T> {
+    type IntoIter = DequeIterator<T>;
+    type Item = T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        DequeIterator(self)
+    }
+}
+
 impl<T> Iterator for DequeIterator<T> {
     type Item = T;
     fn next(&mut self) -> Option<T> {
@@ -203,6 +209,17 @@ mod test {
         assert_eq!(list.pop_back(), None);
     }
 
+    #[test]
+    fn push_back_pop_front() {
+        let mut list = Deque::new();
+        list.push_back(1);
+        list.push_back(2);
+        assert_eq!(&*list.peek_front().unwrap(), &1);
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_front(), Some(2));
+        assert_eq!(list.pop_front(), None);
+    }
+
     #[test]
     fn peek() {
         let mut list = Deque::new();
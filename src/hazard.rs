@@ -0,0 +1,402 @@
+// Hazard pointers, for freeing nodes of the lock-free structures in this crate.
+//
+// A thread that's about to dereference a shared node first publishes the
+// pointer in a hazard record (`Hazard::protect`). A thread that unlinks a node
+// doesn't free it, it `retire`s it, and retired nodes are only freed once no
+// hazard record points at them. That also takes care of ABA: a node someone
+// still holds a hazard for can't be freed, so its address can't be reused.
+//
+// Each `Domain` owns its records and its retired list, so every structure
+// embeds its own. Records are never freed until the domain is dropped, they're
+// just marked inactive and reused by the next `Domain::hazard` call.
+//
+// For the model tests, each call into the domain starts with a `sched::step()`
+// and every further access to shared state inside it is preceded by a
+// `sched::inner_step()`. Models of the structures built on top treat a call as
+// a single step, the models down here interleave everything.
+use std::ptr;
+use std::sync::atomic::{fence, AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+use crate::sched::{inner_step, step};
+
+// How many retired pointers pile up before we try to free some.
+const RECLAIM_THRESHOLD: usize = 64;
+
+struct Record {
+    hazard: AtomicPtr<()>,
+    active: AtomicBool,
+    // Set once before the record is published, never changed after.
+    next: *mut Record,
+}
+
+struct Retired {
+    ptr: *mut (),
+    drop: unsafe fn(*mut ()),
+    next: *mut Retired,
+}
+
+pub(crate) struct Domain {
+    records: AtomicPtr<Record>,
+    retired: AtomicPtr<Retired>,
+    retired_len: AtomicUsize,
+}
+
+// A claimed hazard record. Dropping it clears the hazard and hands the record
+// back to the domain.
+pub(crate) struct Hazard<'a> {
+    record: &'a Record,
+}
+
+unsafe fn drop_box<T>(ptr: *mut ()) {
+    drop(Box::from_raw(ptr.cast::<T>()));
+}
+
+impl Domain {
+    pub(crate) const fn new() -> Self {
+        Domain {
+            records: AtomicPtr::new(ptr::null_mut()),
+            retired: AtomicPtr::new(ptr::null_mut()),
+            retired_len: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn hazard(&self) -> Hazard<'_> {
+        // Try to reuse an inactive record first
+        step();
+        let mut cur = self.records.load(Ordering::Acquire);
+        while let Some(record) = unsafe { cur.as_ref() } {
+            inner_step();
+            if !record.active.load(Ordering::Relaxed)
+                && record
+                    .active
+                    .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+            {
+                return Hazard { record };
+            }
+            cur = record.next;
+        }
+
+        // All taken, so push a fresh one
+        let record = Box::into_raw(Box::new(Record {
+            hazard: AtomicPtr::new(ptr::null_mut()),
+            active: AtomicBool::new(true),
+            next: ptr::null_mut(),
+        }));
+        inner_step();
+        let mut head = self.records.load(Ordering::Relaxed);
+        loop {
+            unsafe { (*record).next = head };
+            inner_step();
+            match self.records.compare_exchange_weak(
+                head,
+                record,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(actual) => head = actual,
+            }
+        }
+        Hazard {
+            record: unsafe { &*record },
+        }
+    }
+
+    // Frees `ptr` (as a `Box<T>`) once no hazard points at it.
+    //
+    // SAFETY: `ptr` must come from `Box::into_raw`, must already be unreachable
+    // for anyone who isn't holding a hazard on it, and must only be retired once.
+    pub(crate) unsafe fn retire<T>(&self, ptr: *mut T) {
+        let retired = Box::into_raw(Box::new(Retired {
+            ptr: ptr.cast(),
+            drop: drop_box::<T>,
+            next: ptr::null_mut(),
+        }));
+        step();
+        // Count it before it's visible, or a concurrent `reclaim` could free it
+        // and decrement first, wrapping the count around.
+        let len = self.retired_len.fetch_add(1, Ordering::Relaxed) + 1;
+        self.push_retired(retired);
+        if len >= RECLAIM_THRESHOLD {
+            self.reclaim();
+        }
+    }
+
+    // Frees every retired pointer that isn't currently protected.
+    pub(crate) fn reclaim(&self) {
+        step();
+        // Pairs with the SeqCst store/load in `protect`: either we see their
+        // hazard, or they see that the node was already unlinked.
+        fence(Ordering::SeqCst);
+
+        let mut hazards = Vec::new();
+        inner_step();
+        let mut cur = self.records.load(Ordering::Acquire);
+        while let Some(record) = unsafe { cur.as_ref() } {
+            inner_step();
+            let hazard = record.hazard.load(Ordering::SeqCst);
+            if !hazard.is_null() {
+                hazards.push(hazard);
+            }
+            cur = record.next;
+        }
+
+        inner_step();
+        let mut cur = self.retired.swap(ptr::null_mut(), Ordering::Acquire);
+        while !cur.is_null() {
+            unsafe {
+                let next = (*cur).next;
+                if hazards.contains(&(*cur).ptr) {
+                    // Still in use, try again next time
+                    self.push_retired(cur);
+                } else {
+                    let retired = Box::from_raw(cur);
+                    (retired.drop)(retired.ptr);
+                    inner_step();
+                    self.retired_len.fetch_sub(1, Ordering::Relaxed);
+                }
+                cur = next;
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn retired_len(&self) -> usize {
+        self.retired_len.load(Ordering::Relaxed)
+    }
+
+    fn push_retired(&self, retired: *mut Retired) {
+        inner_step();
+        let mut head = self.retired.load(Ordering::Relaxed);
+        loop {
+            unsafe { (*retired).next = head };
+            inner_step();
+            match self.retired.compare_exchange_weak(
+                head,
+                retired,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return,
+                Err(actual) => head = actual,
+            }
+        }
+    }
+}
+
+impl Drop for Domain {
+    fn drop(&mut self) {
+        // Nobody else can hold a hazard now, so everything goes
+        let mut cur = *self.retired.get_mut();
+        while !cur.is_null() {
+            unsafe {
+                let retired = Box::from_raw(cur);
+                (retired.drop)(retired.ptr);
+                cur = retired.next;
+            }
+        }
+        let mut cur = *self.records.get_mut();
+        while !cur.is_null() {
+            let record = unsafe { Box::from_raw(cur) };
+            cur = record.next;
+        }
+    }
+}
+
+impl Default for Domain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl Send for Domain {}
+unsafe impl Sync for Domain {}
+
+impl<'a> Hazard<'a> {
+    // Loads `src` and protects the result. Once this returns, the pointer
+    // won't be freed until the hazard is cleared or moved elsewhere.
+    pub(crate) fn protect<T>(&self, src: &AtomicPtr<T>) -> *mut T {
+        step();
+        let mut ptr = src.load(Ordering::Relaxed);
+        loop {
+            inner_step();
+            self.record.hazard.store(ptr.cast(), Ordering::SeqCst);
+            // Make sure it wasn't swapped out (and maybe retired) before we
+            // published the hazard.
+            inner_step();
+            let again = src.load(Ordering::SeqCst);
+            if again == ptr {
+                return ptr;
+            }
+            ptr = again;
+        }
+    }
+
+    // Publishes `ptr` without validating it. The caller has to check that it
+    // is still reachable afterwards before dereferencing it.
+    pub(crate) fn set<T>(&self, ptr: *mut T) {
+        step();
+        self.record.hazard.store(ptr.cast(), Ordering::SeqCst);
+    }
+
+    pub(crate) fn clear(&self) {
+        step();
+        self.record.hazard.store(ptr::null_mut(), Ordering::Release);
+    }
+}
+
+impl<'a> Drop for Hazard<'a> {
+    fn drop(&mut self) {
+        self.clear();
+        inner_step();
+        self.record.active.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Domain;
+    use crate::sched::{explore_inner, step};
+    use crate::test_util::DropCounter;
+    use std::ptr;
+    use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn protected_pointers_survive_reclaim() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let domain = Domain::new();
+        let shared = AtomicPtr::new(Box::into_raw(Box::new(DropCounter(drops.clone()))));
+
+        let hazard = domain.hazard();
+        let ptr = hazard.protect(&shared);
+
+        // Unlink and retire it while the hazard is still up
+        shared.store(std::ptr::null_mut(), Ordering::SeqCst);
+        unsafe { domain.retire(ptr) };
+        domain.reclaim();
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        assert_eq!(domain.retired_len(), 1);
+
+        drop(hazard);
+        domain.reclaim();
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        assert_eq!(domain.retired_len(), 0);
+    }
+
+    #[test]
+    fn records_are_reused() {
+        let domain = Domain::new();
+        let first = domain.hazard().record as *const _;
+        let second = domain.hazard().record as *const _;
+        assert_eq!(first, second);
+
+        let a = domain.hazard();
+        let b = domain.hazard();
+        assert!(!std::ptr::eq(a.record, b.record));
+    }
+
+    #[test]
+    fn drop_frees_retired() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let domain = Domain::new();
+        let hazard = domain.hazard();
+        for _ in 0..10 {
+            let ptr = Box::into_raw(Box::new(DropCounter(drops.clone())));
+            hazard.set(ptr);
+            unsafe { domain.retire(ptr) };
+        }
+        // Only the last one is still protected
+        domain.reclaim();
+        assert_eq!(drops.load(Ordering::SeqCst), 9);
+        drop(hazard);
+        drop(domain);
+        assert_eq!(drops.load(Ordering::SeqCst), 10);
+    }
+
+    #[test]
+    fn threshold_triggers_reclaim() {
+        let domain = Domain::new();
+        for i in 0..1000 {
+            unsafe { domain.retire(Box::into_raw(Box::new(i))) };
+        }
+        assert!(domain.retired_len() < super::RECLAIM_THRESHOLD);
+    }
+
+    // Model tests: interleavings of every access to shared state, `inner_step`s
+    // included.
+    struct Model {
+        domain: Domain,
+        shared: AtomicPtr<DropCounter>,
+        drops: Arc<AtomicUsize>,
+    }
+
+    fn setup() -> Model {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let model = Model {
+            domain: Domain::new(),
+            shared: AtomicPtr::new(Box::into_raw(Box::new(DropCounter(drops.clone())))),
+            drops,
+        };
+        // One record to reuse and one to push, so both ways get explored
+        drop(model.domain.hazard());
+        model
+    }
+
+    #[test]
+    fn model_protect_retire() {
+        let read = |model: &Model| {
+            let hazard = model.domain.hazard();
+            let ptr = hazard.protect(&model.shared);
+            // Whatever the other thread does from here on, it can't free it
+            step();
+            if !ptr.is_null() {
+                assert_eq!(model.drops.load(Ordering::SeqCst), 0);
+            }
+        };
+        let unlink = |model: &Model| {
+            let _busy = model.domain.hazard();
+            step();
+            let ptr = model.shared.swap(ptr::null_mut(), Ordering::SeqCst);
+            unsafe { model.domain.retire(ptr) };
+            model.domain.reclaim();
+        };
+        // Claiming and releasing records makes for too many steps to try every
+        // interleaving, so only the ones with up to four preemptions
+        let runs = explore_inner(Some(4), setup, &[read, unlink], |model, _| {
+            model.domain.reclaim();
+            assert_eq!(model.drops.load(Ordering::SeqCst), 1);
+            assert_eq!(model.domain.retired_len(), 0);
+        });
+        assert!(runs > 1);
+    }
+
+    #[test]
+    fn model_retire_reclaim() {
+        let retire = |model: &Model| {
+            let ptr = Box::into_raw(Box::new(DropCounter(model.drops.clone())));
+            unsafe { model.domain.retire(ptr) };
+        };
+        let reclaim = |model: &Model| {
+            model.domain.reclaim();
+            // The count must never dip below what's really on the list, or it
+            // wraps around
+            assert!(model.domain.retired_len() <= 2);
+        };
+        let setup = || {
+            let model = setup();
+            let ptr = model.shared.swap(ptr::null_mut(), Ordering::SeqCst);
+            unsafe { model.domain.retire(ptr) };
+            model
+        };
+        explore_inner(None, setup, &[retire, reclaim], |model, _| {
+            // However the count and the list got interleaved, they still agree
+            let left = model.domain.retired_len();
+            assert_eq!(model.drops.load(Ordering::SeqCst) + left, 2);
+            model.domain.reclaim();
+            assert_eq!(model.drops.load(Ordering::SeqCst), 2);
+            assert_eq!(model.domain.retired_len(), 0);
+        });
+    }
+}
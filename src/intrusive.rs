@@ -0,0 +1,744 @@
+// An intrusive doubly linked list: the same front/back linking as
+// `sixth::LinkedList`, except the list never allocates. Instead of owning a
+// `Node<T>` with the element inside, each element carries its own `Links`
+// field, and the list threads its pointers through those.
+//
+//     struct Task {
+//         links: Links<Task>,
+//         id: u32,
+//     }
+//
+//     unsafe impl Linked for Task {
+//         type Handle = Pin<Box<Task>>;
+//         fn into_ptr(task: Pin<Box<Task>>) -> NonNull<Task> {
+//             unsafe { NonNull::from(Box::leak(Pin::into_inner_unchecked(task))) }
+//         }
+//         unsafe fn from_ptr(ptr: NonNull<Task>) -> Pin<Box<Task>> {
+//             Pin::new_unchecked(Box::from_raw(ptr.as_ptr()))
+//         }
+//         fn links(&self) -> &Links<Task> {
+//             &self.links
+//         }
+//     }
+//
+// Pushing hands a `Handle` to the list, which keeps it as a raw pointer until
+// the element is popped or removed and the handle is given back. `Handle` is
+// whatever owns or borrows the element on the outside: `Pin<Box<T>>` for an
+// owned element, `Pin<&'static T>` for one that lives somewhere else for good.
+//
+// Safety contract, for implementors of `Linked`:
+// - `links` returns the same `Links` every time it's called on an element.
+// - `from_ptr(into_ptr(handle))` gives back the same handle, and the pointer
+//   stays valid, and the element doesn't move, for as long as the handle is
+//   inside the list. A `Pin`ned handle guarantees the "doesn't move" part.
+// And for users of `List::remove`:
+// - the pointer passed in must be one `push_front`/`push_back` returned (or,
+//   for an element that isn't linked anywhere, one from `Linked::into_ptr`).
+//   A pointer made from a `&T` only allows reading, so it won't do.
+// - the element must be either unlinked or linked into *that* list.
+//
+// Pushing an element that's already in a list panics rather than corrupting
+// both lists.
+use std::fmt;
+use std::marker::{PhantomData, PhantomPinned};
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+
+type Link<T> = Option<NonNull<T>>;
+
+/// Something that can be linked into a `List`, or queued on an `mpsc` queue,
+/// through an embedded `Links`.
+///
+/// # Safety
+///
+/// `links` must return the same `Links` every time it's called on an element,
+/// and must not touch anything else, since `mpsc` producers call it from any
+/// thread. `from_ptr(into_ptr(handle))` must give back the same handle. The
+/// pointer must stay valid, and the element must not move, for as long as it
+/// is inside a list.
+pub unsafe trait Linked: Sized {
+    type Handle;
+
+    fn into_ptr(handle: Self::Handle) -> NonNull<Self>;
+
+    /// # Safety
+    ///
+    /// `ptr` must have come from `into_ptr`, and only be turned back into a
+    /// handle once.
+    unsafe fn from_ptr(ptr: NonNull<Self>) -> Self::Handle;
+
+    fn links(&self) -> &Links<Self>;
+}
+
+// The pointers an element embeds to be part of a list. The list only ever
+// writes them through `&mut List`, but a shared element (`Pin<&'static T>`) can
+// have `is_linked` called on it from any thread meanwhile, so they're atomics.
+// Relaxed is enough: anyone who follows `front`/`back` got to the list through
+// whatever already synchronized them with its owner.
+pub struct Links<T> {
+    front: AtomicPtr<T>,
+    back: AtomicPtr<T>,
+    linked: AtomicBool,
+    // Elements must not move while they're linked
+    _pin: PhantomPinned,
+}
+
+impl<T> Links<T> {
+    pub const fn new() -> Self {
+        Links {
+            front: AtomicPtr::new(ptr::null_mut()),
+            back: AtomicPtr::new(ptr::null_mut()),
+            linked: AtomicBool::new(false),
+            _pin: PhantomPinned,
+        }
+    }
+
+    pub fn is_linked(&self) -> bool {
+        self.linked.load(Ordering::Relaxed)
+    }
+
+    fn front(&self) -> Link<T> {
+        NonNull::new(self.front.load(Ordering::Relaxed))
+    }
+
+    fn back(&self) -> Link<T> {
+        NonNull::new(self.back.load(Ordering::Relaxed))
+    }
+
+    fn set_front(&self, link: Link<T>) {
+        self.front.store(as_ptr(link), Ordering::Relaxed);
+    }
+
+    fn set_back(&self, link: Link<T>) {
+        self.back.store(as_ptr(link), Ordering::Relaxed);
+    }
+}
+
+// `mpsc` threads its queue through `back` and marks queued elements with
+// `linked`, so an element can't be in a list and a queue at once. Its
+// producers and consumer are on different threads, so it picks the orderings.
+impl<T> Links<T> {
+    pub(crate) fn next(&self) -> &AtomicPtr<T> {
+        &self.back
+    }
+
+    pub(crate) fn linked(&self) -> &AtomicBool {
+        &self.linked
+    }
+}
+
+fn as_ptr<T>(link: Link<T>) -> *mut T {
+    link.map_or(ptr::null_mut(), NonNull::as_ptr)
+}
+
+impl<T> Default for Links<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for Links<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Links")
+            .field("linked", &self.is_linked())
+            .finish()
+    }
+}
+
+// SAFETY: `node` must point to a live element.
+unsafe fn links<'a, T: Linked>(node: NonNull<T>) -> &'a Links<T> {
+    (*node.as_ptr()).links()
+}
+
+pub struct List<T: Linked> {
+    front: Link<T>,
+    back: Link<T>,
+    len: usize,
+    _boo: PhantomData<T::Handle>,
+}
+
+impl<T: Linked> List<T> {
+    pub fn new() -> Self {
+        List {
+            front: None,
+            back: None,
+            len: 0,
+            _boo: PhantomData,
+        }
+    }
+
+    // Returns the pointer the list now holds, which is what `remove` takes.
+    pub fn push_front(&mut self, handle: T::Handle) -> NonNull<T> {
+        let new = Self::claim(handle);
+        unsafe {
+            if let Some(old) = self.front {
+                links(old).set_front(Some(new));
+                links(new).set_back(Some(old));
+            } else {
+                self.back = Some(new);
+            }
+        }
+        self.front = Some(new);
+        self.len += 1;
+        new
+    }
+
+    pub fn push_back(&mut self, handle: T::Handle) -> NonNull<T> {
+        let new = Self::claim(handle);
+        unsafe {
+            if let Some(old) = self.back {
+                links(old).set_back(Some(new));
+                links(new).set_front(Some(old));
+            } else {
+                self.front = Some(new);
+            }
+        }
+        self.back = Some(new);
+        self.len += 1;
+        new
+    }
+
+    pub fn pop_front(&mut self) -> Option<T::Handle> {
+        self.front.map(|node| unsafe { self.unlink(node) })
+    }
+
+    pub fn pop_back(&mut self) -> Option<T::Handle> {
+        self.back.map(|node| unsafe { self.unlink(node) })
+    }
+
+    /// Takes `node` out of this list in O(1), wherever it is. Returns `None`
+    /// if it wasn't linked at all.
+    ///
+    /// # Safety
+    ///
+    /// `node` must be a pointer returned by `push_front` or `push_back` (or by
+    /// `Linked::into_ptr`, if the element isn't linked anywhere), and the
+    /// element must still be alive. It must be either unlinked or linked into
+    /// this list (not some other one).
+    pub unsafe fn remove(&mut self, node: NonNull<T>) -> Option<T::Handle> {
+        if links(node).is_linked() {
+            Some(self.unlink(node))
+        } else {
+            None
+        }
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        unsafe { self.front.map(|node| &*node.as_ptr()) }
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        unsafe { self.back.map(|node| &*node.as_ptr()) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            front: self.front,
+            back: self.back,
+            len: self.len,
+            _boo: PhantomData,
+        }
+    }
+
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            cur: None,
+            list: self,
+            index: None,
+        }
+    }
+
+    fn claim(handle: T::Handle) -> NonNull<T> {
+        let node = T::into_ptr(handle);
+        let links = unsafe { links(node) };
+        let linked = links.linked.swap(true, Ordering::Relaxed);
+        assert!(!linked, "element is already linked into a list");
+        links.set_front(None);
+        links.set_back(None);
+        node
+    }
+
+    // SAFETY: `node` must be linked into this list.
+    unsafe fn unlink(&mut self, node: NonNull<T>) -> T::Handle {
+        let (front, back) = (links(node).front(), links(node).back());
+        links(node).set_front(None);
+        links(node).set_back(None);
+        links(node).linked.store(false, Ordering::Relaxed);
+
+        match front {
+            Some(front) => links(front).set_back(back),
+            None => self.front = back,
+        }
+        match back {
+            Some(back) => links(back).set_front(front),
+            None => self.back = front,
+        }
+        self.len -= 1;
+        T::from_ptr(node)
+    }
+
+    // SAFETY: `node` must be linked into this list, `new` must be claimed.
+    unsafe fn link_after(&mut self, node: NonNull<T>, new: NonNull<T>) {
+        let back = links(node).back();
+        links(new).set_front(Some(node));
+        links(new).set_back(back);
+        links(node).set_back(Some(new));
+        match back {
+            Some(back) => links(back).set_front(Some(new)),
+            None => self.back = Some(new),
+        }
+        self.len += 1;
+    }
+
+    // SAFETY: `node` must be linked into this list, `new` must be claimed.
+    unsafe fn link_before(&mut self, node: NonNull<T>, new: NonNull<T>) {
+        let front = links(node).front();
+        links(new).set_back(Some(node));
+        links(new).set_front(front);
+        links(node).set_front(Some(new));
+        match front {
+            Some(front) => links(front).set_back(Some(new)),
+            None => self.front = Some(new),
+        }
+        self.len += 1;
+    }
+}
+
+impl<T: Linked> Drop for List<T> {
+    fn drop(&mut self) {
+        // Hand every element back, so an owning handle frees it
+        self.clear();
+    }
+}
+
+impl<T: Linked> Default for List<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Linked> Extend<T::Handle> for List<T> {
+    fn extend<I: IntoIterator<Item = T::Handle>>(&mut self, iter: I) {
+        for handle in iter {
+            self.push_back(handle);
+        }
+    }
+}
+
+impl<T: Linked + fmt::Debug> fmt::Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+// The list owns its handles, and lends out `&T`.
+unsafe impl<T: Linked + Sync> Send for List<T> where T::Handle: Send {}
+unsafe impl<T: Linked + Sync> Sync for List<T> {}
+
+pub struct Iter<'a, T> {
+    front: Link<T>,
+    back: Link<T>,
+    len: usize,
+    _boo: PhantomData<&'a T>,
+}
+
+impl<'a, T: Linked> IntoIterator for &'a List<T> {
+    type IntoIter = Iter<'a, T>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T: Linked> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.front.map(|node| unsafe {
+            self.len -= 1;
+            self.front = links(node).back();
+            &*node.as_ptr()
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T: Linked> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.back.map(|node| unsafe {
+            self.len -= 1;
+            self.back = links(node).front();
+            &*node.as_ptr()
+        })
+    }
+}
+
+impl<'a, T: Linked> ExactSizeIterator for Iter<'a, T> {}
+
+// Works like `sixth::CursorMut`: it sits either on an element or on the
+// "ghost" position between the back and the front.
+pub struct CursorMut<'a, T: Linked> {
+    cur: Link<T>,
+    list: &'a mut List<T>,
+    index: Option<usize>,
+}
+
+impl<'a, T: Linked> CursorMut<'a, T> {
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    pub fn move_next(&mut self) {
+        match self.cur {
+            Some(cur) => {
+                self.cur = unsafe { links(cur).back() };
+                self.index = self.cur.and_then(|_| self.index.map(|i| i + 1));
+            }
+            None => {
+                self.cur = self.list.front;
+                self.index = self.cur.map(|_| 0);
+            }
+        }
+    }
+
+    pub fn move_prev(&mut self) {
+        match self.cur {
+            Some(cur) => {
+                self.cur = unsafe { links(cur).front() };
+                self.index = self.cur.and_then(|_| self.index.map(|i| i - 1));
+            }
+            None => {
+                self.cur = self.list.back;
+                self.index = self.cur.map(|_| self.list.len - 1);
+            }
+        }
+    }
+
+    pub fn current(&self) -> Option<&T> {
+        unsafe { self.cur.map(|node| &*node.as_ptr()) }
+    }
+
+    pub fn peek_next(&self) -> Option<&T> {
+        let next = match self.cur {
+            Some(cur) => unsafe { links(cur).back() },
+            None => self.list.front,
+        };
+        unsafe { next.map(|node| &*node.as_ptr()) }
+    }
+
+    pub fn peek_prev(&self) -> Option<&T> {
+        let prev = match self.cur {
+            Some(cur) => unsafe { links(cur).front() },
+            None => self.list.back,
+        };
+        unsafe { prev.map(|node| &*node.as_ptr()) }
+    }
+
+    // Unlinks the current element and moves on to the next one.
+    pub fn remove_current(&mut self) -> Option<T::Handle> {
+        let cur = self.cur?;
+        self.cur = unsafe { links(cur).back() };
+        if self.cur.is_none() {
+            self.index = None;
+        }
+        Some(unsafe { self.list.unlink(cur) })
+    }
+
+    // At the ghost, these push onto the back and front of the list.
+    pub fn insert_before(&mut self, handle: T::Handle) {
+        match self.cur {
+            Some(cur) => {
+                let new = List::<T>::claim(handle);
+                unsafe { self.list.link_before(cur, new) };
+                *self.index.as_mut().unwrap() += 1;
+            }
+            None => {
+                self.list.push_back(handle);
+            }
+        }
+    }
+
+    pub fn insert_after(&mut self, handle: T::Handle) {
+        match self.cur {
+            Some(cur) => {
+                let new = List::<T>::claim(handle);
+                unsafe { self.list.link_after(cur, new) };
+            }
+            None => {
+                self.list.push_front(handle);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Linked, Links, List};
+    use std::cell::Cell;
+    use std::pin::Pin;
+    use std::ptr::NonNull;
+    use std::rc::Rc;
+    use std::thread;
+
+    #[derive(Debug)]
+    struct Entry {
+        links: Links<Entry>,
+        value: i32,
+        drops: Option<Rc<Cell<usize>>>,
+    }
+
+    impl Drop for Entry {
+        fn drop(&mut self) {
+            if let Some(drops) = &self.drops {
+                drops.set(drops.get() + 1);
+            }
+        }
+    }
+
+    unsafe impl Linked for Entry {
+        type Handle = Pin<Box<Entry>>;
+
+        fn into_ptr(handle: Self::Handle) -> NonNull<Self> {
+            unsafe { NonNull::from(Box::leak(Pin::into_inner_unchecked(handle))) }
+        }
+
+        unsafe fn from_ptr(ptr: NonNull<Self>) -> Self::Handle {
+            Pin::new_unchecked(Box::from_raw(ptr.as_ptr()))
+        }
+
+        fn links(&self) -> &Links<Self> {
+            &self.links
+        }
+    }
+
+    fn entry(value: i32) -> Pin<Box<Entry>> {
+        Box::pin(Entry {
+            links: Links::new(),
+            value,
+            drops: None,
+        })
+    }
+
+    fn values(list: &List<Entry>) -> Vec<i32> {
+        list.iter().map(|e| e.value).collect()
+    }
+
+    #[test]
+    fn push_pop() {
+        let mut list = List::<Entry>::new();
+        assert!(list.pop_front().is_none());
+        assert!(list.pop_back().is_none());
+
+        list.push_back(entry(2));
+        list.push_back(entry(3));
+        list.push_front(entry(1));
+        assert_eq!(list.len(), 3);
+        assert_eq!(values(&list), &[1, 2, 3]);
+        assert_eq!(list.front().map(|e| e.value), Some(1));
+        assert_eq!(list.back().map(|e| e.value), Some(3));
+        assert_eq!(
+            list.iter().rev().map(|e| e.value).collect::<Vec<_>>(),
+            &[3, 2, 1]
+        );
+
+        let popped = list.pop_back().unwrap();
+        assert_eq!(popped.value, 3);
+        assert!(!popped.links.is_linked());
+        assert_eq!(list.pop_front().unwrap().value, 1);
+        assert_eq!(list.pop_front().unwrap().value, 2);
+        assert!(list.is_empty());
+
+        // A popped element can go straight back in
+        list.push_front(popped);
+        assert_eq!(values(&list), &[3]);
+    }
+
+    #[test]
+    fn remove() {
+        let mut list = List::<Entry>::new();
+        let ptrs: Vec<_> = (0..5).map(|i| list.push_back(entry(i))).collect();
+
+        unsafe {
+            assert_eq!(list.remove(ptrs[2]).unwrap().value, 2);
+            assert_eq!(values(&list), &[0, 1, 3, 4]);
+            assert_eq!(list.remove(ptrs[0]).unwrap().value, 0);
+            assert_eq!(list.remove(ptrs[4]).unwrap().value, 4);
+        }
+        assert_eq!(values(&list), &[1, 3]);
+        assert_eq!(list.len(), 2);
+
+        // Unlinked elements are left alone
+        let ptr = Entry::into_ptr(entry(9));
+        assert!(unsafe { list.remove(ptr) }.is_none());
+        drop(unsafe { Entry::from_ptr(ptr) });
+    }
+
+    #[test]
+    #[should_panic(expected = "already linked")]
+    fn double_link() {
+        let mut a = List::new();
+        let mut b = List::<Entry>::new();
+        let ptr = a.push_back(entry(1));
+        // Forge a second handle to an element that's still in `a`
+        let forged = unsafe { Entry::from_ptr(ptr) };
+        let forged = std::mem::ManuallyDrop::new(forged);
+        b.push_back(unsafe { std::ptr::read(&*forged) });
+    }
+
+    #[test]
+    fn cursor() {
+        let mut list = List::<Entry>::new();
+        list.extend((1..=4).map(entry));
+
+        let mut cursor = list.cursor_mut();
+        assert!(cursor.current().is_none());
+        assert_eq!(cursor.peek_next().map(|e| e.value), Some(1));
+        assert_eq!(cursor.peek_prev().map(|e| e.value), Some(4));
+
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.current().map(|e| e.value), Some(2));
+        assert_eq!(cursor.index(), Some(1));
+
+        cursor.insert_before(entry(10));
+        assert_eq!(cursor.index(), Some(2));
+        cursor.insert_after(entry(20));
+        assert_eq!(cursor.peek_next().map(|e| e.value), Some(20));
+
+        let removed = cursor.remove_current().unwrap();
+        assert_eq!(removed.value, 2);
+        assert_eq!(cursor.current().map(|e| e.value), Some(20));
+        assert_eq!(cursor.index(), Some(2));
+
+        cursor.move_prev();
+        cursor.move_prev();
+        cursor.move_prev();
+        assert!(cursor.current().is_none());
+        assert_eq!(cursor.index(), None);
+        cursor.insert_after(entry(0));
+        cursor.insert_before(entry(99));
+        cursor.move_prev();
+        assert_eq!(cursor.index(), Some(6));
+
+        assert_eq!(values(&list), &[0, 1, 10, 20, 3, 4, 99]);
+        assert_eq!(list.len(), 7);
+    }
+
+    #[test]
+    fn drop_hands_back() {
+        let drops = Rc::new(Cell::new(0));
+        let mut list = List::<Entry>::new();
+        for value in 0..3 {
+            list.push_back(Box::pin(Entry {
+                links: Links::new(),
+                value,
+                drops: Some(drops.clone()),
+            }));
+        }
+        drop(list.pop_front());
+        assert_eq!(drops.get(), 1);
+        drop(list);
+        assert_eq!(drops.get(), 3);
+    }
+
+    // Elements that live in statics and are shared between threads.
+    struct Shared {
+        links: Links<Shared>,
+        value: i32,
+    }
+
+    unsafe impl Linked for Shared {
+        type Handle = Pin<&'static Shared>;
+
+        fn into_ptr(handle: Self::Handle) -> NonNull<Self> {
+            NonNull::from(handle.get_ref())
+        }
+
+        unsafe fn from_ptr(ptr: NonNull<Self>) -> Self::Handle {
+            Pin::static_ref(&*ptr.as_ptr())
+        }
+
+        fn links(&self) -> &Links<Self> {
+            &self.links
+        }
+    }
+
+    static SHARED: [Shared; 3] = [
+        Shared {
+            links: Links::new(),
+            value: 1,
+        },
+        Shared {
+            links: Links::new(),
+            value: 2,
+        },
+        Shared {
+            links: Links::new(),
+            value: 3,
+        },
+    ];
+
+    #[test]
+    fn shared_across_threads() {
+        let mut list = List::<Shared>::new();
+        list.push_back(Pin::static_ref(&SHARED[0]));
+        list.push_back(Pin::static_ref(&SHARED[1]));
+
+        // Relinking while other threads look at whether it's linked
+        thread::scope(|s| {
+            for _ in 0..2 {
+                s.spawn(|| {
+                    for _ in 0..10 {
+                        let _ = SHARED[2].links.is_linked();
+                    }
+                });
+            }
+            for _ in 0..10 {
+                list.push_back(Pin::static_ref(&SHARED[2]));
+                list.pop_back();
+            }
+        });
+
+        // Iterating the same list from two threads at once
+        list.push_back(Pin::static_ref(&SHARED[2]));
+        let list = &list;
+        thread::scope(|s| {
+            let sums: Vec<_> = (0..2)
+                .map(|_| s.spawn(move || list.iter().map(|e| e.value).sum::<i32>()))
+                .collect();
+            for sum in sums {
+                assert_eq!(sum.join().unwrap(), 6);
+            }
+        });
+    }
+
+    #[test]
+    fn debug() {
+        let mut list = List::<Entry>::new();
+        list.push_back(entry(1));
+        assert!(format!("{:?}", list).contains("value: 1"));
+        assert!(format!("{:?}", list).contains("linked: true"));
+    }
+}
@@ -1,10 +1,28 @@
+#[macro_use]
+mod macros;
+
+pub mod channel;
+mod convert;
+pub mod fifth;
 pub mod first;
-pub mod second;
-pub mod third;
 pub mod fourth; // very sus
-pub mod fifth;
+mod hazard;
+pub mod intrusive;
+pub mod mpsc;
+pub mod ms_queue;
+mod sched;
+pub mod second;
 pub mod sixth;
+pub mod skiplist;
+pub mod third;
+pub mod treiber;
+pub mod unrolled;
+pub mod xor;
 
 // random meme lists
+pub mod array_allocated;
 pub mod double;
-pub mod stack_allocated;
\ No newline at end of file
+pub mod stack_allocated;
+
+#[cfg(test)]
+mod test_util;
@@ -0,0 +1,133 @@
+// Construction macros, in the spirit of `vec!`. Each one takes either a list
+// of elements, `[a, b, c]`, or a repeated element, `[x; n]` (which needs
+// `x: Clone`, and evaluates `x` once).
+//
+// The elements always come out in the order they're written:
+//   list![1, 2, 3]   sixth::LinkedList, front to back
+//   deque![1, 2, 3]  fourth::Deque, front to back
+//   queue![1, 2, 3]  fifth::UnsafeQueue, 1 is popped first
+//   plist![1, 2, 3]  third::List, 1 is the head
+
+#[macro_export]
+macro_rules! list {
+    ($elem:expr; $n:expr) => {
+        ::std::iter::repeat($elem)
+            .take($n)
+            .collect::<$crate::sixth::LinkedList<_>>()
+    };
+    ($($x:expr),* $(,)?) => {
+        <$crate::sixth::LinkedList<_> as ::std::iter::FromIterator<_>>::from_iter([$($x),*])
+    };
+}
+
+#[macro_export]
+macro_rules! deque {
+    ($elem:expr; $n:expr) => {{
+        let mut deque = $crate::fourth::Deque::new();
+        for elem in ::std::iter::repeat($elem).take($n) {
+            deque.push_back(elem);
+        }
+        deque
+    }};
+    ($($x:expr),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut deque = $crate::fourth::Deque::new();
+        $(deque.push_back($x);)*
+        deque
+    }};
+}
+
+#[macro_export]
+macro_rules! queue {
+    ($elem:expr; $n:expr) => {{
+        let mut queue = $crate::fifth::UnsafeQueue::new();
+        for elem in ::std::iter::repeat($elem).take($n) {
+            queue.push(elem);
+        }
+        queue
+    }};
+    ($($x:expr),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut queue = $crate::fifth::UnsafeQueue::new();
+        $(queue.push($x);)*
+        queue
+    }};
+}
+
+#[macro_export]
+macro_rules! plist {
+    ($elem:expr; $n:expr) => {{
+        let mut list = $crate::third::List::new();
+        for elem in ::std::iter::repeat($elem).take($n) {
+            list = list.prepend(elem);
+        }
+        list
+    }};
+    ($($x:expr),* $(,)?) => {{
+        // Persistent lists grow at the head, so prepend from the last element.
+        #[allow(unused_mut)]
+        let mut list = $crate::third::List::new();
+        for elem in ::std::iter::IntoIterator::into_iter([$($x),*]).rev() {
+            list = list.prepend(elem);
+        }
+        list
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sixth::LinkedList;
+
+    #[test]
+    fn list() {
+        let list = list![1, 2, 3];
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), &[1, 2, 3]);
+
+        let empty: LinkedList<i32> = list![];
+        assert!(empty.is_empty());
+
+        let repeated = list![String::from("a"); 3];
+        assert_eq!(repeated.len(), 3);
+        assert!(repeated.iter().all(|s| s == "a"));
+        assert_eq!(list![0; 0], LinkedList::new());
+    }
+
+    #[test]
+    fn deque() {
+        let deque = deque![1, 2, 3,];
+        assert_eq!(deque.into_iter().collect::<Vec<_>>(), &[1, 2, 3]);
+
+        let mut repeated = deque![7; 2];
+        assert_eq!(repeated.pop_front(), Some(7));
+        assert_eq!(repeated.pop_back(), Some(7));
+        assert_eq!(repeated.pop_front(), None);
+    }
+
+    #[test]
+    fn queue() {
+        let mut queue = queue![1, 2, 3];
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.pop(), Some(2));
+        assert_eq!(queue.pop(), Some(3));
+        assert_eq!(queue.pop(), None);
+
+        let repeated = queue![vec![1]; 2];
+        assert_eq!(repeated.iter().count(), 2);
+        let mut empty = queue![];
+        empty.push(1);
+        assert_eq!(empty.pop(), Some(1));
+    }
+
+    #[test]
+    fn plist() {
+        let list = plist![1, 2, 3];
+        assert_eq!(list.head(), Some(&1));
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), &[1, 2, 3]);
+        assert_eq!(list.tail().head(), Some(&2));
+
+        let repeated = plist!['x'; 3];
+        assert_eq!(repeated.iter().collect::<String>(), "xxx");
+        let empty: crate::third::List<i32> = plist![];
+        assert_eq!(empty.head(), None);
+    }
+}
@@ -0,0 +1,500 @@
+// Dmitry Vyukov's intrusive multi-producer single-consumer queue.
+//
+// Same shape as `fifth::UnsafeQueue`: pop from `head`, push at `tail`. But like
+// `intrusive::List`, the queue never allocates: elements implement the same
+// `intrusive::Linked`, and the queue threads its `next` pointers through their
+// `intrusive::Links`.
+//
+//     struct Message {
+//         links: Links<Message>,
+//         body: String,
+//     }
+//
+//     unsafe impl Linked for Message {
+//         type Handle = Pin<Box<Message>>;
+//         fn into_ptr(msg: Pin<Box<Message>>) -> NonNull<Message> {
+//             unsafe { NonNull::from(Box::leak(Pin::into_inner_unchecked(msg))) }
+//         }
+//         unsafe fn from_ptr(ptr: NonNull<Message>) -> Pin<Box<Message>> {
+//             Pin::new_unchecked(Box::from_raw(ptr.as_ptr()))
+//         }
+//         fn links(&self) -> &Links<Message> {
+//             &self.links
+//         }
+//     }
+//
+// The queue always holds one extra `stub` element so that neither end is ever
+// null. The caller hands one over when making the queue, and gets it back
+// (dropped) with the queue. The stub moves around: whenever the consumer would
+// take the last element out, it pushes the stub back in behind it first.
+//
+// A push is a single `swap` of `tail` followed by a store to the old tail's
+// `next`, so producers never wait on anyone. The price is that between those
+// two steps the chain is broken: the new element is the tail but nothing links
+// to it yet. A consumer that runs into that gap gets `PopResult::Inconsistent`,
+// meaning "there is more, but a producer is halfway through adding it", as
+// opposed to `PopResult::Empty`.
+//
+// Only one thread may pop, so the queue comes as a cloneable `Producer` and a
+// single `Consumer`. Pushing an element that's already queued, or linked into
+// an `intrusive::List`, panics.
+use std::cell::UnsafeCell;
+use std::hint;
+use std::marker::PhantomData;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::Arc;
+
+use crate::intrusive::Linked;
+
+// Only ever borrowed for the one atomic access.
+unsafe fn next<'a, T: Linked>(node: *mut T) -> &'a AtomicPtr<T> {
+    (*node).links().next()
+}
+
+struct Queue<T: Linked> {
+    // Consumer side, only ever touched through the `Consumer`
+    head: UnsafeCell<*mut T>,
+    // Producer side
+    tail: AtomicPtr<T>,
+    stub: *mut T,
+    _boo: PhantomData<T::Handle>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PopResult<T> {
+    Data(T),
+    Empty,
+    // A push is in progress and hasn't linked its element yet. Trying again
+    // shortly will find it.
+    Inconsistent,
+}
+
+pub struct Producer<T: Linked> {
+    queue: Arc<Queue<T>>,
+}
+
+pub struct Consumer<T: Linked> {
+    queue: Arc<Queue<T>>,
+}
+
+// `stub` is never handed out, it only keeps the queue from running dry.
+pub fn queue<T: Linked>(stub: T::Handle) -> (Producer<T>, Consumer<T>) {
+    let stub = Queue::<T>::claim(stub);
+    let queue = Arc::new(Queue {
+        head: UnsafeCell::new(stub),
+        tail: AtomicPtr::new(stub),
+        stub,
+        _boo: PhantomData,
+    });
+    (
+        Producer {
+            queue: queue.clone(),
+        },
+        Consumer { queue },
+    )
+}
+
+impl<T: Linked> Queue<T> {
+    fn claim(handle: T::Handle) -> *mut T {
+        let node = T::into_ptr(handle).as_ptr();
+        let queued = unsafe { (*node).links().linked() };
+        assert!(
+            !queued.swap(true, Ordering::AcqRel),
+            "element is already queued"
+        );
+        node
+    }
+
+    fn push_node(&self, node: *mut T) {
+        unsafe { next(node).store(ptr::null_mut(), Ordering::Relaxed) };
+        let prev = self.tail.swap(node, Ordering::AcqRel);
+        // The chain is broken until this store lands
+        unsafe { next(prev).store(node, Ordering::Release) };
+    }
+
+    // SAFETY: only one thread may call this at a time.
+    unsafe fn pop(&self) -> PopResult<T::Handle> {
+        let head = &mut *self.head.get();
+        let mut first = *head;
+        let mut next = next(first).load(Ordering::Acquire);
+
+        // Skip over the stub if it's in front
+        if first == self.stub {
+            if next.is_null() {
+                return PopResult::Empty;
+            }
+            *head = next;
+            first = next;
+            next = self::next(next).load(Ordering::Acquire);
+        }
+
+        if !next.is_null() {
+            *head = next;
+            return PopResult::Data(Self::take(first));
+        }
+
+        // `first` looks like the last element. If it isn't really the tail, a
+        // producer has swapped the tail but not linked its element to us yet.
+        if first != self.tail.load(Ordering::Acquire) {
+            return PopResult::Inconsistent;
+        }
+
+        // Put the stub behind `first`, so taking `first` doesn't leave the
+        // queue without an element.
+        self.push_node(self.stub);
+        next = self::next(first).load(Ordering::Acquire);
+        if !next.is_null() {
+            *head = next;
+            return PopResult::Data(Self::take(first));
+        }
+        // Someone pushed between our tail check and the stub push
+        PopResult::Inconsistent
+    }
+
+    // SAFETY: `node` must be out of the chain, and nobody else may touch it.
+    unsafe fn take(node: *mut T) -> T::Handle {
+        (*node).links().linked().store(false, Ordering::Release);
+        T::from_ptr(NonNull::new_unchecked(node))
+    }
+}
+
+impl<T: Linked> Drop for Queue<T> {
+    fn drop(&mut self) {
+        // Every handle is gone, so every push has finished and the chain is
+        // whole again. Hand each element back, so an owning handle frees it.
+        let mut cur = *self.head.get_mut();
+        while !cur.is_null() {
+            let next = unsafe { next(cur).load(Ordering::Relaxed) };
+            if cur != self.stub {
+                drop(unsafe { Self::take(cur) });
+            }
+            cur = next;
+        }
+        drop(unsafe { Self::take(self.stub) });
+    }
+}
+
+// Elements only move from producers to the consumer, never get shared.
+unsafe impl<T: Linked> Send for Queue<T> where T::Handle: Send {}
+unsafe impl<T: Linked> Sync for Queue<T> where T::Handle: Send {}
+
+impl<T: Linked> Producer<T> {
+    // Never blocks or retries, whatever the other threads are doing.
+    pub fn push(&self, handle: T::Handle) {
+        self.queue.push_node(Queue::<T>::claim(handle));
+    }
+}
+
+impl<T: Linked> Clone for Producer<T> {
+    fn clone(&self) -> Self {
+        Producer {
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+impl<T: Linked> Consumer<T> {
+    pub fn pop(&mut self) -> PopResult<T::Handle> {
+        // `&mut self` on the only `Consumer` makes us the only popper
+        unsafe { self.queue.pop() }
+    }
+
+    // Like `pop`, but spins through `Inconsistent` until the producer that
+    // caused it finishes. Only `None` when the queue is really empty.
+    pub fn pop_spin(&mut self) -> Option<T::Handle> {
+        loop {
+            match self.pop() {
+                PopResult::Data(handle) => return Some(handle),
+                PopResult::Empty => return None,
+                PopResult::Inconsistent => hint::spin_loop(),
+            }
+        }
+    }
+}
+
+impl<T: Linked> Iterator for Consumer<T> {
+    type Item = T::Handle;
+
+    // Stops at the first `Empty`, the queue can still be popped afterwards.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.pop_spin()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{queue, Consumer, PopResult, Queue};
+    use crate::intrusive::{Linked, Links, List};
+    use std::pin::Pin;
+    use std::ptr::NonNull;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    struct Entry {
+        links: Links<Entry>,
+        value: usize,
+        drops: Option<Arc<AtomicUsize>>,
+    }
+
+    impl Drop for Entry {
+        fn drop(&mut self) {
+            if let Some(drops) = &self.drops {
+                drops.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    unsafe impl Linked for Entry {
+        type Handle = Pin<Box<Entry>>;
+
+        fn into_ptr(handle: Self::Handle) -> NonNull<Self> {
+            unsafe { NonNull::from(Box::leak(Pin::into_inner_unchecked(handle))) }
+        }
+
+        unsafe fn from_ptr(ptr: NonNull<Self>) -> Self::Handle {
+            Pin::new_unchecked(Box::from_raw(ptr.as_ptr()))
+        }
+
+        fn links(&self) -> &Links<Self> {
+            &self.links
+        }
+    }
+
+    fn entry(value: usize) -> Pin<Box<Entry>> {
+        Box::pin(Entry {
+            links: Links::new(),
+            value,
+            drops: None,
+        })
+    }
+
+    fn pop(consumer: &mut Consumer<Entry>) -> PopResult<usize> {
+        match consumer.pop() {
+            PopResult::Data(entry) => {
+                assert!(!entry.links.is_linked());
+                PopResult::Data(entry.value)
+            }
+            PopResult::Empty => PopResult::Empty,
+            PopResult::Inconsistent => PopResult::Inconsistent,
+        }
+    }
+
+    #[test]
+    fn basics() {
+        let (producer, mut consumer) = queue::<Entry>(entry(0));
+        assert_eq!(pop(&mut consumer), PopResult::Empty);
+
+        producer.push(entry(1));
+        producer.push(entry(2));
+        assert_eq!(pop(&mut consumer), PopResult::Data(1));
+        producer.push(entry(3));
+        assert_eq!(pop(&mut consumer), PopResult::Data(2));
+        assert_eq!(pop(&mut consumer), PopResult::Data(3));
+        assert_eq!(pop(&mut consumer), PopResult::Empty);
+
+        // Refilling after the stub went back in
+        producer.clone().push(entry(4));
+        let popped = consumer.pop_spin().unwrap();
+        assert_eq!(popped.value, 4);
+        assert!(consumer.pop_spin().is_none());
+
+        // A popped element can go straight back in
+        producer.push(popped);
+        for i in 5..10 {
+            producer.push(entry(i));
+        }
+        assert_eq!(
+            consumer.by_ref().map(|e| e.value).collect::<Vec<_>>(),
+            &[4, 5, 6, 7, 8, 9]
+        );
+    }
+
+    // Borrowed rather than owned, so the same element can be pushed twice.
+    struct Shared {
+        links: Links<Shared>,
+    }
+
+    unsafe impl Linked for Shared {
+        type Handle = Pin<&'static Shared>;
+
+        fn into_ptr(handle: Self::Handle) -> NonNull<Self> {
+            NonNull::from(Pin::get_ref(handle))
+        }
+
+        unsafe fn from_ptr(ptr: NonNull<Self>) -> Self::Handle {
+            Pin::new_unchecked(&*ptr.as_ptr())
+        }
+
+        fn links(&self) -> &Links<Self> {
+            &self.links
+        }
+    }
+
+    fn leak_shared() -> Pin<&'static Shared> {
+        Pin::static_ref(Box::leak(Box::new(Shared {
+            links: Links::new(),
+        })))
+    }
+
+    #[test]
+    #[should_panic(expected = "already queued")]
+    fn double_push() {
+        let (producer, _consumer) = queue::<Shared>(leak_shared());
+        let shared = leak_shared();
+        producer.push(shared);
+        producer.push(shared);
+    }
+
+    #[test]
+    #[should_panic(expected = "already queued")]
+    fn push_while_in_list() {
+        let (producer, _consumer) = queue::<Shared>(leak_shared());
+        let shared = leak_shared();
+        let mut list = List::<Shared>::new();
+        list.push_back(shared);
+        producer.push(shared);
+    }
+
+    #[test]
+    fn requeue_shared() {
+        let (producer, mut consumer) = queue::<Shared>(leak_shared());
+        let shared = leak_shared();
+        producer.push(shared);
+        let popped = consumer.pop_spin().unwrap();
+        assert!(ptr_eq(popped, shared));
+        assert!(!shared.links.is_linked());
+        producer.push(shared);
+        assert!(shared.links.is_linked());
+    }
+
+    fn ptr_eq(a: Pin<&Shared>, b: Pin<&Shared>) -> bool {
+        std::ptr::eq(a.get_ref(), b.get_ref())
+    }
+
+    #[test]
+    fn inconsistent() {
+        let (producer, mut consumer) = queue::<Entry>(entry(0));
+        producer.push(entry(1));
+        producer.push(entry(2));
+
+        // Replay a producer that gets preempted between its swap and its link
+        let node = Queue::<Entry>::claim(entry(3));
+        let prev = producer.queue.tail.swap(node, Ordering::SeqCst);
+
+        // 1 has a successor, so it can go. 2 looks like the last element, but
+        // the tail has moved past it.
+        assert_eq!(pop(&mut consumer), PopResult::Data(1));
+        assert_eq!(pop(&mut consumer), PopResult::Inconsistent);
+        assert_eq!(pop(&mut consumer), PopResult::Inconsistent);
+
+        // The producer wakes up and finishes
+        unsafe { (*prev).links.next().store(node, Ordering::SeqCst) };
+        assert_eq!(pop(&mut consumer), PopResult::Data(2));
+        assert_eq!(pop(&mut consumer), PopResult::Data(3));
+        assert_eq!(pop(&mut consumer), PopResult::Empty);
+    }
+
+    #[test]
+    fn drops_everything_once() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let counted = || {
+            Box::pin(Entry {
+                links: Links::new(),
+                value: 0,
+                drops: Some(drops.clone()),
+            })
+        };
+        let (producer, mut consumer) = queue::<Entry>(counted());
+        for _ in 0..10 {
+            producer.push(counted());
+        }
+        for _ in 0..4 {
+            drop(consumer.pop_spin());
+        }
+        assert_eq!(drops.load(Ordering::SeqCst), 4);
+        drop(consumer);
+        assert_eq!(drops.load(Ordering::SeqCst), 4);
+        // The producer can outlive the consumer, the queue (and the stub) go
+        // with the last
+        producer.push(counted());
+        drop(producer);
+        assert_eq!(drops.load(Ordering::SeqCst), 12);
+    }
+
+    #[test]
+    fn many_producers() {
+        const PRODUCERS: usize = 16;
+        const PER_PRODUCER: usize = 10_000;
+
+        let (producer, mut consumer) = queue::<Entry>(entry(0));
+        let barrier = Arc::new(Barrier::new(PRODUCERS));
+        let producers: Vec<_> = (0..PRODUCERS)
+            .map(|p| {
+                let producer = producer.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    for i in 0..PER_PRODUCER {
+                        producer.push(entry(p * PER_PRODUCER + i));
+                    }
+                })
+            })
+            .collect();
+        drop(producer);
+
+        let mut next = [0; PRODUCERS];
+        let mut received = 0;
+        while received < PRODUCERS * PER_PRODUCER {
+            match pop(&mut consumer) {
+                PopResult::Data(value) => {
+                    // Each producer's elements come out in the order it pushed them
+                    let (p, i) = (value / PER_PRODUCER, value % PER_PRODUCER);
+                    assert_eq!(i, next[p]);
+                    next[p] += 1;
+                    received += 1;
+                }
+                PopResult::Empty | PopResult::Inconsistent => thread::yield_now(),
+            }
+        }
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        assert_eq!(next, [PER_PRODUCER; PRODUCERS]);
+        assert_eq!(pop(&mut consumer), PopResult::Empty);
+    }
+
+    #[test]
+    fn consumer_on_another_thread() {
+        const PRODUCERS: usize = 8;
+        const PER_PRODUCER: usize = 5_000;
+
+        let (producer, mut consumer) = queue::<Entry>(entry(0));
+        let consumer = thread::spawn(move || {
+            let mut sum = 0;
+            let mut count = 0;
+            while count < PRODUCERS * PER_PRODUCER {
+                match consumer.pop_spin() {
+                    Some(entry) => {
+                        sum += entry.value;
+                        count += 1;
+                    }
+                    None => thread::yield_now(),
+                }
+            }
+            sum
+        });
+        let producers: Vec<_> = (0..PRODUCERS)
+            .map(|_| {
+                let producer = producer.clone();
+                thread::spawn(move || (0..PER_PRODUCER).for_each(|i| producer.push(entry(i))))
+            })
+            .collect();
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        let expected = PRODUCERS * (PER_PRODUCER * (PER_PRODUCER - 1) / 2);
+        assert_eq!(consumer.join().unwrap(), expected);
+    }
+}
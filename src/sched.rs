@@ -0,0 +1,385 @@
+// A tiny deterministic scheduler, for model checking the lock-free structures.
+//
+// The code under test calls `step()` right before each access to shared state.
+// Outside of tests that compiles to nothing. Under `explore`, every operation
+// runs on its own thread, but only one of them is ever allowed to move: at each
+// `step()` the running thread parks and the explorer picks who goes next. Every
+// pick is recorded, and after each run the explorer backtracks to the last pick
+// that still had an untried option, so it ends up walking the whole tree of
+// interleavings depth first.
+//
+// Since only one thread runs at a time, this only ever sees sequentially
+// consistent executions. What weaker orderings can do is left to the stress
+// tests and miri.
+//
+// The tree grows exponentially with the number of steps, so there are two
+// ways to keep it in check. `inner_step()` marks accesses inside a call that
+// has already been checked on its own (the hazard pointer domain, say): those
+// only yield under `explore_inner`, plain `explore` runs the whole call as one
+// step. And a bound on preemptions (switching away from a thread that could
+// have kept going) keeps bigger trees tractable. Most concurrency bugs need
+// only one or two preemptions to show up.
+#[cfg(not(test))]
+#[inline(always)]
+pub(crate) fn step() {}
+
+#[cfg(not(test))]
+#[inline(always)]
+pub(crate) fn inner_step() {}
+
+#[cfg(test)]
+pub(crate) use self::model::{explore, explore_inner, inner_step, step};
+
+#[cfg(test)]
+mod model {
+    use std::cell::RefCell;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+    use std::thread;
+
+    // No operation in this crate takes anywhere near this many steps, so a
+    // schedule that does is stuck in a loop.
+    const MAX_STEPS: usize = 100_000;
+
+    struct Control {
+        state: Mutex<State>,
+        turn: Condvar,
+        // Whether `inner_step` yields too
+        inner: bool,
+    }
+
+    struct State {
+        // The thread that may run right now. `None` while the explorer picks.
+        running: Option<usize>,
+        finished: Vec<bool>,
+        // Set when a schedule is given up on, so parked threads bail out
+        abandoned: bool,
+    }
+
+    impl Control {
+        fn lock(&self) -> MutexGuard<'_, State> {
+            // A panicking operation shouldn't take the rest of the run with it
+            self.state.lock().unwrap_or_else(|e| e.into_inner())
+        }
+
+        fn wait_turn(&self, mut state: MutexGuard<'_, State>, id: usize) {
+            while state.running != Some(id) {
+                if state.abandoned {
+                    drop(state);
+                    panic!("schedule abandoned");
+                }
+                state = self.turn.wait(state).unwrap_or_else(|e| e.into_inner());
+            }
+        }
+    }
+
+    thread_local! {
+        static CURRENT: RefCell<Option<(Arc<Control>, usize)>> = const { RefCell::new(None) };
+    }
+
+    // Hands control back to the explorer, if this thread is being explored.
+    // Other threads (every other test, for one) just carry on.
+    pub(crate) fn step() {
+        let current = CURRENT.with(|current| current.borrow().clone());
+        if let Some((control, id)) = current {
+            let mut state = control.lock();
+            state.running = None;
+            control.turn.notify_all();
+            control.wait_turn(state, id);
+        }
+    }
+
+    pub(crate) fn inner_step() {
+        let inner = CURRENT.with(|current| {
+            let current = current.borrow();
+            current.as_ref().is_some_and(|(control, _)| control.inner)
+        });
+        if inner {
+            step();
+        }
+    }
+
+    // Marks a thread finished however its operation ends, panics included.
+    struct Finish<'a>(&'a Control, usize);
+
+    impl Drop for Finish<'_> {
+        fn drop(&mut self) {
+            CURRENT.with(|current| current.borrow_mut().take());
+            let mut state = self.0.lock();
+            state.finished[self.1] = true;
+            state.running = None;
+            self.0.turn.notify_all();
+        }
+    }
+
+    // The picks of the current run, and where we are in replaying them.
+    struct Path {
+        // (index picked, number of options) at every point with a real choice
+        picks: Vec<(usize, usize)>,
+        pos: usize,
+    }
+
+    impl Path {
+        fn choose(&mut self, options: usize) -> usize {
+            if options == 1 {
+                return 0;
+            }
+            if self.pos == self.picks.len() {
+                self.picks.push((0, options));
+            }
+            let (pick, n) = self.picks[self.pos];
+            assert_eq!(n, options, "operations must be deterministic");
+            self.pos += 1;
+            pick
+        }
+
+        // Moves on to the next unexplored schedule, if there is one.
+        fn advance(&mut self) -> bool {
+            self.pos = 0;
+            while let Some((pick, n)) = self.picks.pop() {
+                if pick + 1 < n {
+                    self.picks.push((pick + 1, n));
+                    return true;
+                }
+            }
+            false
+        }
+    }
+
+    // Runs `ops` concurrently on a fresh `setup()`, once for every
+    // interleaving of their steps (with at most `preemptions` preemptions, if
+    // given), and hands the state and the results in `ops` order to `check`.
+    // Panics with the offending schedule if an operation or `check` does.
+    // Returns how many schedules it ran.
+    pub(crate) fn explore<S, R>(
+        preemptions: Option<usize>,
+        setup: impl Fn() -> S,
+        ops: &[fn(&S) -> R],
+        check: impl Fn(S, Vec<R>),
+    ) -> usize
+    where
+        S: Sync,
+        R: Send,
+    {
+        explore_with(false, preemptions, setup, ops, check)
+    }
+
+    // Like `explore`, but `inner_step`s yield as well.
+    pub(crate) fn explore_inner<S, R>(
+        preemptions: Option<usize>,
+        setup: impl Fn() -> S,
+        ops: &[fn(&S) -> R],
+        check: impl Fn(S, Vec<R>),
+    ) -> usize
+    where
+        S: Sync,
+        R: Send,
+    {
+        explore_with(true, preemptions, setup, ops, check)
+    }
+
+    fn explore_with<S, R>(
+        inner: bool,
+        preemptions: Option<usize>,
+        setup: impl Fn() -> S,
+        ops: &[fn(&S) -> R],
+        check: impl Fn(S, Vec<R>),
+    ) -> usize
+    where
+        S: Sync,
+        R: Send,
+    {
+        let mut path = Path {
+            picks: Vec::new(),
+            pos: 0,
+        };
+        let mut runs = 0;
+        loop {
+            runs += 1;
+            let mut schedule = Vec::new();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                let state = setup();
+                let results = run(&state, ops, inner, preemptions, &mut path, &mut schedule);
+                check(state, results);
+            }));
+            if result.is_err() {
+                panic!("failed on run {runs}, thread per step: {schedule:?}");
+            }
+            if !path.advance() {
+                return runs;
+            }
+        }
+    }
+
+    fn run<S: Sync, R: Send>(
+        state: &S,
+        ops: &[fn(&S) -> R],
+        inner: bool,
+        preemptions: Option<usize>,
+        path: &mut Path,
+        schedule: &mut Vec<usize>,
+    ) -> Vec<R> {
+        let control = Arc::new(Control {
+            state: Mutex::new(State {
+                running: None,
+                finished: vec![false; ops.len()],
+                abandoned: false,
+            }),
+            turn: Condvar::new(),
+            inner,
+        });
+
+        thread::scope(|s| {
+            let threads: Vec<_> = ops
+                .iter()
+                .enumerate()
+                .map(|(id, op)| {
+                    let control = control.clone();
+                    s.spawn(move || {
+                        let _finish = Finish(&control, id);
+                        CURRENT.with(|current| *current.borrow_mut() = Some((control.clone(), id)));
+                        control.wait_turn(control.lock(), id);
+                        op(state)
+                    })
+                })
+                .collect();
+
+            // Let each thread in turn run up to its first step. Nothing before
+            // that touches shared state, so the order doesn't matter.
+            for id in 0..ops.len() {
+                let mut state = control.lock();
+                state.running = Some(id);
+                control.turn.notify_all();
+                while state.running.is_some() {
+                    state = control.turn.wait(state).unwrap_or_else(|e| e.into_inner());
+                }
+            }
+
+            let mut last: Option<usize> = None;
+            let mut preempted = 0;
+            loop {
+                let mut state = control.lock();
+                while state.running.is_some() {
+                    state = control.turn.wait(state).unwrap_or_else(|e| e.into_inner());
+                }
+
+                // Keeping the last thread going comes first, so the first run
+                // is the one without any preemptions.
+                let mut options: Vec<usize> =
+                    last.filter(|&id| !state.finished[id]).into_iter().collect();
+                let can_preempt =
+                    options.is_empty() || preemptions.is_none_or(|max| preempted < max);
+                if can_preempt {
+                    options.extend(
+                        (0..ops.len()).filter(|&id| !state.finished[id] && Some(id) != last),
+                    );
+                }
+                if options.is_empty() {
+                    break;
+                }
+
+                let next = options[path.choose(options.len())];
+                if last.is_some_and(|id| id != next && !state.finished[id]) {
+                    preempted += 1;
+                }
+                schedule.push(next);
+                if schedule.len() > MAX_STEPS {
+                    state.abandoned = true;
+                    control.turn.notify_all();
+                    drop(state);
+                    panic!("no progress after {MAX_STEPS} steps");
+                }
+                state.running = Some(next);
+                last = Some(next);
+                control.turn.notify_all();
+            }
+
+            threads
+                .into_iter()
+                .map(|thread| thread.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{explore, explore_inner, inner_step, step};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Two unsynchronized increments: load, then store.
+    fn increment(count: &AtomicUsize) -> usize {
+        step();
+        let seen = count.load(Ordering::SeqCst);
+        step();
+        count.store(seen + 1, Ordering::SeqCst);
+        seen
+    }
+
+    #[test]
+    fn finds_every_interleaving() {
+        let lost = AtomicUsize::new(0);
+        let runs = explore(
+            None,
+            || AtomicUsize::new(0),
+            &[increment, increment],
+            |count, _| {
+                if count.into_inner() == 1 {
+                    lost.fetch_add(1, Ordering::SeqCst);
+                }
+            },
+        );
+        // 4 steps, 2 from each thread: 4 choose 2 orders, and in all but the
+        // two sequential ones an update gets lost
+        assert_eq!(runs, 6);
+        assert_eq!(lost.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn bounds_preemptions() {
+        let runs = explore(
+            Some(0),
+            || AtomicUsize::new(0),
+            &[increment, increment],
+            |count, results| {
+                assert_eq!(count.into_inner(), 2);
+                assert!(results == [0, 1] || results == [1, 0]);
+            },
+        );
+        assert_eq!(runs, 2);
+    }
+
+    // Same again, but as one outer step with the store as an inner one.
+    fn increment_inner(count: &AtomicUsize) -> usize {
+        step();
+        let seen = count.load(Ordering::SeqCst);
+        inner_step();
+        count.store(seen + 1, Ordering::SeqCst);
+        seen
+    }
+
+    #[test]
+    fn inner_steps() {
+        let ops: [fn(&AtomicUsize) -> usize; 2] = [increment_inner, increment_inner];
+        let runs = explore(
+            None,
+            || AtomicUsize::new(0),
+            &ops,
+            |count, _| assert_eq!(count.into_inner(), 2),
+        );
+        assert_eq!(runs, 2);
+        let runs = explore_inner(None, || AtomicUsize::new(0), &ops, |_, _| {});
+        assert_eq!(runs, 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "failed on run")]
+    fn reports_failures() {
+        explore(
+            None,
+            || AtomicUsize::new(0),
+            &[increment, increment],
+            |count, _| assert_eq!(count.into_inner(), 2),
+        );
+    }
+}
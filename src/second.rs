@@ -1,14 +1,37 @@
-struct Node<T> {
+// `#[repr(C)]` so this has the same layout as the other singly linked nodes
+// in the crate, which lets `convert` hand whole chains between lists.
+#[repr(C)]
+pub(crate) struct Node<T> {
     elem: T,
     next: Link<T>,
 }
 
 type Link<T> = Option<Box<Node<T>>>;
 
-pub struct ListIterator<'a, T> {
+pub struct IntoIter<T>(List<T>);
+
+pub struct Iter<'a, T> {
     next: Option<&'a Node<T>>,
 }
-impl<'a, T> Iterator for ListIterator<'a, T> {
+
+pub struct IterMut<'a, T> {
+    next: Option<&'a mut Node<T>>,
+}
+
+#[deprecated(note = "renamed to `Iter`")]
+pub type ListIterator<'a, T> = Iter<'a, T>;
+
+#[deprecated(note = "renamed to `IterMut`")]
+pub type ListIteratorMut<'a, T> = IterMut<'a, T>;
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop()
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
     type Item = &'a T;
     fn next(&mut self) -> Option<Self::Item> {
         self.next.take().map(|node| {
@@ -18,10 +41,7 @@ impl<'a, T> Iterator for ListIterator<'a, T> {
     }
 }
 
-pub struct ListIteratorMut<'a, T> {
-    next: Option<&'a mut Node<T>>,
-}
-impl<'a, T> Iterator for ListIteratorMut<'a, T> {
+impl<'a, T> Iterator for IterMut<'a, T> {
     type Item = &'a mut T;
     fn next(&mut self) -> Option<Self::Item> {
         self.next.take().map(|node| {
@@ -33,11 +53,12 @@ impl<'a, T> Iterator for ListIteratorMut<'a, T> {
 
 pub struct List<T> {
     head: Link<T>,
+    len: usize,
 }
 impl<T> List<T> {
     // Returns an empty list.
     pub fn new() -> Self {
-        List { head: None }
+        List { head: None, len: 0 }
     }
 
     // Pushes an elment to the front of the list.
@@ -47,16 +68,119 @@ impl<T> List<T> {
             next: self.head.take(),
         });
         self.head = Some(new_node);
+        self.len += 1;
     }
 
     // Pops the first element from the list.
     pub fn pop(&mut self) -> Option<T> {
         self.head.take().map(|node| {
             self.head = node.next;
+            self.len -= 1;
             node.elem
         })
     }
 
+    // Returns the number of elements in the list.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Returns a reference to the element `n` places from the front.
+    pub fn nth(&self, n: usize) -> Option<&T> {
+        self.iter().nth(n)
+    }
+
+    // Returns a mutable reference to the element `n` places from the front.
+    pub fn nth_mut(&mut self, n: usize) -> Option<&mut T> {
+        self.iter_mut().nth(n)
+    }
+
+    // Reverses the list in place by relinking the existing nodes.
+    pub fn reverse(&mut self) {
+        let mut reversed = None;
+        let mut cur_link = self.head.take();
+        while let Some(mut boxed_node) = cur_link {
+            cur_link = boxed_node.next.take();
+            boxed_node.next = reversed;
+            reversed = Some(boxed_node);
+        }
+        self.head = reversed;
+    }
+
+    // Keeps the first `at` elements and returns the rest as a new list.
+    // Panics if `at > len`.
+    pub fn split_off(&mut self, at: usize) -> List<T> {
+        assert!(
+            at <= self.len,
+            "split index (is {}) should be <= len (is {})",
+            at,
+            self.len
+        );
+        let mut link = &mut self.head;
+        for _ in 0..at {
+            link = &mut link.as_mut().unwrap().next;
+        }
+        let rest = List {
+            head: link.take(),
+            len: self.len - at,
+        };
+        self.len = at;
+        rest
+    }
+
+    // Moves the first `n` elements to the back, like `VecDeque::rotate_left`.
+    // Panics if `n > len`.
+    pub fn rotate_left(&mut self, n: usize) {
+        let mut rest = self.split_off(n);
+        rest.append(self);
+        *self = rest;
+    }
+
+    // Moves the last `n` elements to the front, like `VecDeque::rotate_right`.
+    // Panics if `n > len`.
+    pub fn rotate_right(&mut self, n: usize) {
+        assert!(
+            n <= self.len,
+            "rotate count (is {}) should be <= len (is {})",
+            n,
+            self.len
+        );
+        self.rotate_left(self.len - n);
+    }
+
+    // Gives up the node chain, for `convert`.
+    pub(crate) fn into_raw(mut self) -> (*mut Node<T>, usize) {
+        let head = self.head.take().map_or(std::ptr::null_mut(), Box::into_raw);
+        (head, self.len)
+    }
+
+    // Takes over a node chain, for `convert`.
+    //
+    // SAFETY: `head` must be null or the start of a chain of exactly `len`
+    // boxed nodes that nothing else owns.
+    pub(crate) unsafe fn from_raw(head: *mut Node<T>, len: usize) -> Self {
+        let head = if head.is_null() {
+            None
+        } else {
+            Some(Box::from_raw(head))
+        };
+        List { head, len }
+    }
+
+    // Moves every node of `other` onto the end of this list, leaving `other` empty.
+    pub fn append(&mut self, other: &mut List<T>) {
+        let mut link = &mut self.head;
+        while link.is_some() {
+            link = &mut link.as_mut().unwrap().next;
+        }
+        *link = other.head.take();
+        self.len += std::mem::take(&mut other.len);
+    }
+
     // Returns a reference to the first element of the list.
     pub fn peek(&self) -> Option<&T> {
         self.head.as_ref().map(|node| &node.elem)
@@ -67,20 +191,32 @@ impl<T> List<T> {
         self.head.as_mut().map(|node| &mut node.elem)
     }
 
-    // Returns the linked list as an iterator
-    pub fn into_iter(&self) -> ListIterator<'_, T> {
+    // Returns an iterator over references to the elements, front first.
+    pub fn iter(&self) -> Iter<'_, T> {
         // the '_ means it's hiding an elided lifetime
-        ListIterator {
+        Iter {
             next: self.head.as_deref(),
         }
     }
 
-    // Returns the linked list as a mutable iterator
-    pub fn into_iter_mut(&mut self) -> ListIteratorMut<'_, T> {
-        ListIteratorMut {
+    // Returns an iterator over mutable references to the elements, front first.
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
             next: self.head.as_deref_mut(),
         }
     }
+
+    // Despite the name, this only borrows the list.
+    #[deprecated(note = "this borrows the list, use `iter` instead")]
+    pub fn into_iter(&self) -> Iter<'_, T> {
+        self.iter()
+    }
+
+    // Despite the name, this only borrows the list.
+    #[deprecated(note = "this borrows the list, use `iter_mut` instead")]
+    pub fn into_iter_mut(&mut self) -> IterMut<'_, T> {
+        self.iter_mut()
+    }
 }
 impl<T> Drop for List<T> {
     fn drop(&mut self) {
@@ -96,6 +232,33 @@ impl<T> Default for List<T> {
     }
 }
 
+impl<T> IntoIterator for List<T> {
+    type IntoIter = IntoIter<T>;
+    type Item = T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a List<T> {
+    type IntoIter = Iter<'a, T>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut List<T> {
+    type IntoIter = IterMut<'a, T>;
+    type Item = &'a mut T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::List;
@@ -164,7 +327,9 @@ mod tests {
 
         assert_eq!(list.peek(), Some(&3));
         assert_eq!(list.peek_mut(), Some(&mut 3));
-        list.peek_mut().map(|value| *value = 42);
+        if let Some(value) = list.peek_mut() {
+            *value = 42;
+        }
 
         assert_eq!(list.peek(), Some(&42));
         assert_eq!(list.pop(), Some(42));
@@ -178,10 +343,30 @@ mod tests {
         list.push(3);
 
         let mut iter = list.into_iter();
+        assert_eq!(iter.next(), Some(3));
+        assert_eq!(iter.next(), Some(2));
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn iter() {
+        let mut list = List::new();
+        list.push(1);
+        list.push(2);
+        list.push(3);
+
+        let mut iter = list.iter();
         assert_eq!(iter.next(), Some(&3));
         assert_eq!(iter.next(), Some(&2));
         assert_eq!(iter.next(), Some(&1));
         assert_eq!(iter.next(), None);
+
+        let mut sum = 0;
+        for elem in &list {
+            sum += elem;
+        }
+        assert_eq!(sum, 6);
     }
 
     #[test]
@@ -191,9 +376,149 @@ mod tests {
         list.push(2);
         list.push(3);
 
-        let mut iter = list.into_iter_mut();
+        let mut iter = list.iter_mut();
         assert_eq!(iter.next(), Some(&mut 3));
         assert_eq!(iter.next(), Some(&mut 2));
         assert_eq!(iter.next(), Some(&mut 1));
+
+        for elem in &mut list {
+            *elem *= 10;
+        }
+        assert_eq!(list.peek(), Some(&30));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_borrowing_iters() {
+        let mut list = List::new();
+        list.push(1);
+        list.push(2);
+
+        let list_ref = &mut list;
+        assert_eq!(list_ref.into_iter_mut().next(), Some(&mut 2));
+        let list_ref = &list;
+        let mut iter: super::ListIterator<'_, i32> = list_ref.into_iter();
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next(), None);
+    }
+
+    // Deliberately not Clone, so the tests below can't be copying elements.
+    #[derive(Debug, PartialEq)]
+    struct Token(u32);
+
+    fn addresses(list: &List<Token>) -> Vec<*const Token> {
+        list.iter().map(|token| token as *const Token).collect()
+    }
+
+    #[test]
+    fn len() {
+        let mut list = List::new();
+        assert_eq!(list.len(), 0);
+        assert!(list.is_empty());
+        list.push(1);
+        list.push(2);
+        assert_eq!(list.len(), 2);
+        list.pop();
+        assert_eq!(list.len(), 1);
+    }
+
+    #[test]
+    fn nth() {
+        let mut list = List::new();
+        list.push(1);
+        list.push(2);
+        list.push(3);
+
+        assert_eq!(list.nth(0), Some(&3));
+        assert_eq!(list.nth(2), Some(&1));
+        assert_eq!(list.nth(3), None);
+        *list.nth_mut(1).unwrap() = 20;
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), &[3, 20, 1]);
+        assert_eq!(list.nth_mut(3), None);
+    }
+
+    #[test]
+    fn reverse() {
+        let mut list = List::new();
+        list.reverse();
+        assert_eq!(list.pop(), None::<Token>);
+
+        for i in 0..4 {
+            list.push(Token(i));
+        }
+        let before = addresses(&list);
+        list.reverse();
+        let mut after = addresses(&list);
+        after.reverse();
+
+        // Same allocations, opposite order
+        assert_eq!(before, after);
+        assert_eq!(list.len(), 4);
+        assert_eq!(list.pop(), Some(Token(0)));
+        assert_eq!(list.pop(), Some(Token(1)));
+    }
+
+    #[test]
+    fn split_off_append() {
+        let mut list = List::new();
+        for i in 0..5 {
+            list.push(Token(i));
+        }
+        let before = addresses(&list);
+
+        let mut rest = list.split_off(2);
+        assert_eq!(list.len(), 2);
+        assert_eq!(rest.len(), 3);
+        assert_eq!(addresses(&list), &before[..2]);
+        assert_eq!(addresses(&rest), &before[2..]);
+        assert_eq!(rest.peek(), Some(&Token(2)));
+
+        // Splitting at either end
+        assert_eq!(list.split_off(2).len(), 0);
+        let mut all = rest.split_off(0);
+        assert!(rest.is_empty());
+
+        list.append(&mut all);
+        assert!(all.is_empty());
+        assert_eq!(list.len(), 5);
+        assert_eq!(addresses(&list), before);
+
+        list.append(&mut all);
+        assert_eq!(list.len(), 5);
+        all.append(&mut list);
+        assert_eq!(addresses(&all), before);
+    }
+
+    #[test]
+    fn rotate() {
+        let mut list = List::new();
+        for i in (0..5).rev() {
+            list.push(Token(i));
+        }
+        let before = addresses(&list);
+
+        list.rotate_left(2);
+        assert_eq!(
+            list.iter().map(|t| t.0).collect::<Vec<_>>(),
+            &[2, 3, 4, 0, 1]
+        );
+        list.rotate_right(3);
+        assert_eq!(
+            list.iter().map(|t| t.0).collect::<Vec<_>>(),
+            &[4, 0, 1, 2, 3]
+        );
+        list.rotate_left(1);
+        list.rotate_right(0);
+        list.rotate_left(5);
+        assert_eq!(addresses(&list), before);
+    }
+
+    #[test]
+    #[should_panic]
+    fn split_off_out_of_bounds() {
+        let mut list = List::new();
+        list.push(1);
+        list.split_off(2);
     }
 }
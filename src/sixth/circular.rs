@@ -0,0 +1,712 @@
+// A circular version of `LinkedList`, for round-robin use: the back's `back`
+// pointer wraps around to the front and vice versa, so there's no `None`
+// anywhere in the ring and no ghost position for cursors.
+//
+// Instead of a front and a back the list keeps a `current` node. Rotating just
+// moves `current` along the ring; no nodes get popped and pushed again. The
+// node before `current` is the back, i.e. the one whose turn is furthest away.
+//
+// Uses the same `Node` as `LinkedList`, so converting between the two relinks
+// the existing nodes.
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr::NonNull;
+
+use super::{Link, LinkedList, Node};
+
+pub struct CircularList<T> {
+    cur: Link<T>,
+    len: usize,
+    _boo: PhantomData<T>,
+}
+
+impl<T> CircularList<T> {
+    pub fn new() -> Self {
+        CircularList {
+            cur: None,
+            len: 0,
+            _boo: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Whose turn it is.
+    pub fn current(&self) -> Option<&T> {
+        unsafe { self.cur.map(|node| &(*node.as_ptr()).elem) }
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut T> {
+        unsafe { self.cur.map(|node| &mut (*node.as_ptr()).elem) }
+    }
+
+    // The element right before `current`, whose turn comes last.
+    pub fn back(&self) -> Option<&T> {
+        unsafe { self.cur.map(|node| &(*Self::prev(node).as_ptr()).elem) }
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        unsafe { self.cur.map(|node| &mut (*Self::prev(node).as_ptr()).elem) }
+    }
+
+    // Adds `elem` at the end of the round, just before `current`.
+    pub fn push_back(&mut self, elem: T) {
+        let new = Node::alloc(elem);
+        match self.cur {
+            Some(cur) => unsafe { self.link_before(cur, new) },
+            None => unsafe { self.link_alone(new) },
+        }
+    }
+
+    // Adds `elem` and makes it `current`.
+    pub fn push_front(&mut self, elem: T) {
+        self.push_back(elem);
+        self.rotate_backward(1);
+    }
+
+    // Removes `current`. The next element becomes `current`.
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.cur.map(|node| unsafe { self.unlink(node) })
+    }
+
+    // Removes the element before `current`.
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.cur
+            .map(|node| unsafe { self.unlink(Self::prev(node)) })
+    }
+
+    // Moves `current` `n` places forward. Goes whichever way round is
+    // shorter, so it never takes more than len / 2 steps.
+    pub fn rotate_forward(&mut self, n: usize) {
+        if self.len == 0 {
+            return;
+        }
+        let n = n % self.len;
+        if n <= self.len / 2 {
+            self.step(n, true);
+        } else {
+            self.step(self.len - n, false);
+        }
+    }
+
+    // Moves `current` `n` places backward.
+    pub fn rotate_backward(&mut self, n: usize) {
+        if self.len == 0 {
+            return;
+        }
+        self.rotate_forward(self.len - n % self.len);
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+
+    // Iterates once around the ring, starting at `current`.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            front: self.cur,
+            back: self.cur.map(|node| unsafe { Self::prev(node) }),
+            len: self.len,
+            _boo: PhantomData,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            front: self.cur,
+            back: self.cur.map(|node| unsafe { Self::prev(node) }),
+            len: self.len,
+            _boo: PhantomData,
+        }
+    }
+
+    // Starts out on `current`.
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            cur: self.cur,
+            index: 0,
+            list: self,
+        }
+    }
+
+    fn step(&mut self, n: usize, forward: bool) {
+        if let Some(mut node) = self.cur {
+            for _ in 0..n {
+                node = unsafe {
+                    if forward {
+                        Self::next(node)
+                    } else {
+                        Self::prev(node)
+                    }
+                };
+            }
+            self.cur = Some(node);
+        }
+    }
+
+    // Every node in a ring has both neighbours, so these never see `None`.
+    unsafe fn next(node: NonNull<Node<T>>) -> NonNull<Node<T>> {
+        (*node.as_ptr()).back.unwrap()
+    }
+
+    unsafe fn prev(node: NonNull<Node<T>>) -> NonNull<Node<T>> {
+        (*node.as_ptr()).front.unwrap()
+    }
+
+    // SAFETY: the list must be empty.
+    unsafe fn link_alone(&mut self, new: NonNull<Node<T>>) {
+        (*new.as_ptr()).front = Some(new);
+        (*new.as_ptr()).back = Some(new);
+        self.cur = Some(new);
+        self.len = 1;
+    }
+
+    // SAFETY: `at` must be in this ring, `new` must be unlinked.
+    unsafe fn link_before(&mut self, at: NonNull<Node<T>>, new: NonNull<Node<T>>) {
+        let prev = Self::prev(at);
+        (*new.as_ptr()).front = Some(prev);
+        (*new.as_ptr()).back = Some(at);
+        (*prev.as_ptr()).back = Some(new);
+        (*at.as_ptr()).front = Some(new);
+        self.len += 1;
+    }
+
+    // SAFETY: `node` must be in this ring.
+    unsafe fn unlink(&mut self, node: NonNull<Node<T>>) -> T {
+        if self.len == 1 {
+            self.cur = None;
+        } else {
+            let (prev, next) = (Self::prev(node), Self::next(node));
+            (*prev.as_ptr()).back = Some(next);
+            (*next.as_ptr()).front = Some(prev);
+            if self.cur == Some(node) {
+                self.cur = Some(next);
+            }
+        }
+        self.len -= 1;
+        Box::from_raw(node.as_ptr()).elem
+    }
+}
+
+impl<T> Drop for CircularList<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T> Default for CircularList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Clone for CircularList<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T> Extend<T> for CircularList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.push_back(elem);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for CircularList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for CircularList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+// Two rings are equal if they hold the same elements starting from `current`.
+impl<T: PartialEq> PartialEq for CircularList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other)
+    }
+}
+
+impl<T: Eq> Eq for CircularList<T> {}
+
+// Closes the list into a ring, with its front as `current`.
+impl<T> From<LinkedList<T>> for CircularList<T> {
+    fn from(mut list: LinkedList<T>) -> Self {
+        let len = mem::replace(&mut list.len, 0);
+        match (list.front.take(), list.back.take()) {
+            (Some(front), Some(back)) => unsafe {
+                (*front.as_ptr()).front = Some(back);
+                (*back.as_ptr()).back = Some(front);
+                CircularList {
+                    cur: Some(front),
+                    len,
+                    _boo: PhantomData,
+                }
+            },
+            _ => CircularList::new(),
+        }
+    }
+}
+
+// Cuts the ring open just before `current`, which becomes the front.
+impl<T> From<CircularList<T>> for LinkedList<T> {
+    fn from(mut ring: CircularList<T>) -> Self {
+        let mut list = LinkedList::new();
+        if let Some(front) = ring.cur.take() {
+            unsafe {
+                let back = CircularList::prev(front);
+                (*front.as_ptr()).front = None;
+                (*back.as_ptr()).back = None;
+                list.front = Some(front);
+                list.back = Some(back);
+            }
+            list.len = mem::replace(&mut ring.len, 0);
+        }
+        list
+    }
+}
+
+unsafe impl<T: Send> Send for CircularList<T> {}
+unsafe impl<T: Sync> Sync for CircularList<T> {}
+
+pub struct IntoIter<T>(CircularList<T>);
+
+pub struct Iter<'a, T> {
+    front: Link<T>,
+    back: Link<T>,
+    len: usize,
+    _boo: PhantomData<&'a T>,
+}
+
+pub struct IterMut<'a, T> {
+    front: Link<T>,
+    back: Link<T>,
+    len: usize,
+    _boo: PhantomData<&'a mut T>,
+}
+
+impl<T> IntoIterator for CircularList<T> {
+    type IntoIter = IntoIter<T>;
+    type Item = T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a CircularList<T> {
+    type IntoIter = Iter<'a, T>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut CircularList<T> {
+    type IntoIter = IterMut<'a, T>;
+    type Item = &'a mut T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+// The ring never runs out of links, so the iterators stop on `len` alone.
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.front.map(|node| unsafe {
+            self.len -= 1;
+            self.front = (*node.as_ptr()).back;
+            &(*node.as_ptr()).elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.back.map(|node| unsafe {
+            self.len -= 1;
+            self.back = (*node.as_ptr()).front;
+            &(*node.as_ptr()).elem
+        })
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.front.map(|node| unsafe {
+            self.len -= 1;
+            self.front = (*node.as_ptr()).back;
+            &mut (*node.as_ptr()).elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.back.map(|node| unsafe {
+            self.len -= 1;
+            self.back = (*node.as_ptr()).front;
+            &mut (*node.as_ptr()).elem
+        })
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {}
+
+unsafe impl<'a, T: Sync> Send for Iter<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for Iter<'a, T> {}
+
+unsafe impl<'a, T: Send> Send for IterMut<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for IterMut<'a, T> {}
+
+// A cursor that walks round and round the ring. It's only ever off an element
+// when the list is empty. `index` counts from the list's `current`.
+pub struct CursorMut<'a, T> {
+    cur: Link<T>,
+    list: &'a mut CircularList<T>,
+    index: usize,
+}
+
+impl<'a, T> CursorMut<'a, T> {
+    pub fn index(&self) -> Option<usize> {
+        self.cur.map(|_| self.index)
+    }
+
+    pub fn move_next(&mut self) {
+        if let Some(cur) = self.cur {
+            self.cur = Some(unsafe { CircularList::next(cur) });
+            self.index = (self.index + 1) % self.list.len;
+        }
+    }
+
+    pub fn move_prev(&mut self) {
+        if let Some(cur) = self.cur {
+            self.cur = Some(unsafe { CircularList::prev(cur) });
+            self.index = (self.index + self.list.len - 1) % self.list.len;
+        }
+    }
+
+    pub fn current(&mut self) -> Option<&mut T> {
+        unsafe { self.cur.map(|node| &mut (*node.as_ptr()).elem) }
+    }
+
+    // In a one element ring, the next and previous elements are the current one.
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        unsafe {
+            self.cur
+                .map(|node| &mut (*CircularList::next(node).as_ptr()).elem)
+        }
+    }
+
+    pub fn peek_prev(&mut self) -> Option<&mut T> {
+        unsafe {
+            self.cur
+                .map(|node| &mut (*CircularList::prev(node).as_ptr()).elem)
+        }
+    }
+
+    // Makes the element under the cursor the list's `current`.
+    pub fn make_current(&mut self) {
+        self.list.cur = self.cur;
+        self.index = 0;
+    }
+
+    // Inserts between the cursor and the element before it. On the list's
+    // `current` that's the end of the round. In an empty list the new element
+    // becomes the cursor's (and the list's) current.
+    pub fn insert_before(&mut self, elem: T) {
+        let new = Node::alloc(elem);
+        match self.cur {
+            Some(cur) => unsafe {
+                self.list.link_before(cur, new);
+                if self.index > 0 {
+                    self.index += 1;
+                }
+            },
+            None => unsafe {
+                self.list.link_alone(new);
+                self.cur = Some(new);
+            },
+        }
+    }
+
+    pub fn insert_after(&mut self, elem: T) {
+        let new = Node::alloc(elem);
+        match self.cur {
+            Some(cur) => unsafe { self.list.link_before(CircularList::next(cur), new) },
+            None => unsafe {
+                self.list.link_alone(new);
+                self.cur = Some(new);
+            },
+        }
+    }
+
+    // Removes the element under the cursor and moves on to the next one.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let cur = self.cur?;
+        let elem = unsafe {
+            self.cur = Some(CircularList::next(cur));
+            self.list.unlink(cur)
+        };
+        if self.list.is_empty() {
+            self.cur = None;
+            self.index = 0;
+        } else if self.index == self.list.len {
+            // We were on the last one, so we wrapped round to `current`
+            self.index = 0;
+        }
+        Some(elem)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CircularList;
+    use crate::sixth::LinkedList;
+
+    fn ring(v: &[i32]) -> CircularList<i32> {
+        v.iter().copied().collect()
+    }
+
+    fn values(list: &CircularList<i32>) -> Vec<i32> {
+        list.iter().copied().collect()
+    }
+
+    #[test]
+    fn basics() {
+        let mut list = CircularList::new();
+        assert_eq!(list.pop_front(), None);
+        assert_eq!(list.pop_back(), None);
+        assert_eq!(list.current(), None);
+
+        list.push_back(2);
+        list.push_back(3);
+        list.push_front(1);
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.current(), Some(&1));
+        assert_eq!(list.back(), Some(&3));
+        assert_eq!(values(&list), &[1, 2, 3]);
+        assert_eq!(list.iter().rev().copied().collect::<Vec<_>>(), &[3, 2, 1]);
+
+        *list.current_mut().unwrap() = 10;
+        *list.back_mut().unwrap() = 30;
+        assert_eq!(list.pop_front(), Some(10));
+        assert_eq!(list.current(), Some(&2));
+        assert_eq!(list.pop_back(), Some(30));
+        assert_eq!(list.pop_back(), Some(2));
+        assert!(list.is_empty());
+        assert_eq!(list.current(), None);
+    }
+
+    #[test]
+    fn rotate() {
+        let mut list = ring(&[0, 1, 2, 3, 4]);
+        list.rotate_forward(1);
+        assert_eq!(values(&list), &[1, 2, 3, 4, 0]);
+        list.rotate_forward(4);
+        assert_eq!(values(&list), &[0, 1, 2, 3, 4]);
+        list.rotate_backward(2);
+        assert_eq!(values(&list), &[3, 4, 0, 1, 2]);
+        list.rotate_forward(12);
+        assert_eq!(values(&list), &[0, 1, 2, 3, 4]);
+        list.rotate_backward(5);
+        assert_eq!(list.current(), Some(&0));
+
+        let mut empty = CircularList::<i32>::new();
+        empty.rotate_forward(3);
+        empty.rotate_backward(3);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn round_robin() {
+        let mut tasks: CircularList<_> = ["a", "b", "c"].into_iter().map(|s| (s, 2)).collect();
+        let mut order = String::new();
+        while let Some((name, left)) = tasks.current_mut() {
+            order.push_str(name);
+            *left -= 1;
+            if *left == 0 {
+                tasks.pop_front();
+            } else {
+                tasks.rotate_forward(1);
+            }
+        }
+        assert_eq!(order, "abcabc");
+    }
+
+    #[test]
+    fn cursor_wraps() {
+        let mut list = ring(&[1, 2, 3]);
+        let mut cursor = list.cursor_mut();
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(cursor.current(), Some(&mut 1));
+        assert_eq!(cursor.peek_prev(), Some(&mut 3));
+
+        cursor.move_prev();
+        assert_eq!(cursor.index(), Some(2));
+        assert_eq!(cursor.current(), Some(&mut 3));
+        assert_eq!(cursor.peek_next(), Some(&mut 1));
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.index(), Some(1));
+        assert_eq!(cursor.current(), Some(&mut 2));
+
+        // Around the whole ring lands back in the same place
+        for _ in 0..3 {
+            cursor.move_next();
+        }
+        assert_eq!(cursor.current(), Some(&mut 2));
+
+        cursor.make_current();
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(values(&list), &[2, 3, 1]);
+    }
+
+    #[test]
+    fn cursor_edits() {
+        let mut list = ring(&[1, 2, 3]);
+        let mut cursor = list.cursor_mut();
+
+        // Before `current` means at the end of the round
+        cursor.insert_before(4);
+        assert_eq!(cursor.index(), Some(0));
+        cursor.move_next();
+        cursor.insert_before(10);
+        assert_eq!(cursor.index(), Some(2));
+        cursor.insert_after(20);
+        assert_eq!(cursor.peek_next(), Some(&mut 20));
+
+        // Removing the last one wraps round to `current`
+        cursor.move_prev();
+        cursor.move_prev();
+        cursor.move_prev();
+        assert_eq!(cursor.index(), Some(5));
+        assert_eq!(cursor.remove_current(), Some(4));
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(cursor.current(), Some(&mut 1));
+
+        // Removing `current` moves the list's `current` along too
+        assert_eq!(cursor.remove_current(), Some(1));
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(values(&list), &[10, 2, 20, 3]);
+
+        let mut list = ring(&[7]);
+        let mut cursor = list.cursor_mut();
+        assert_eq!(cursor.peek_next(), Some(&mut 7));
+        assert_eq!(cursor.remove_current(), Some(7));
+        assert_eq!(cursor.index(), None);
+        assert_eq!(cursor.current(), None);
+        cursor.move_next();
+        cursor.insert_after(8);
+        assert_eq!(cursor.current(), Some(&mut 8));
+        assert_eq!(values(&list), &[8]);
+    }
+
+    #[test]
+    fn iterators() {
+        let mut list = ring(&[1, 2, 3]);
+        for elem in &mut list {
+            *elem *= 10;
+        }
+        let mut iter = list.iter();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next(), Some(&10));
+        assert_eq!(iter.next_back(), Some(&30));
+        assert_eq!(iter.next(), Some(&20));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        let mut into = list.clone().into_iter();
+        assert_eq!(into.next_back(), Some(30));
+        assert_eq!(into.collect::<Vec<_>>(), &[10, 20]);
+        assert_eq!(format!("{:?}", list), "[10, 20, 30]");
+        assert_eq!(list, ring(&[10, 20, 30]));
+        assert_ne!(list, ring(&[20, 30, 10]));
+    }
+
+    #[test]
+    fn from_linked_list() {
+        let list: LinkedList<_> = (0..4).map(|i| i.to_string()).collect();
+        let addresses = list.iter().map(|s| s as *const String).collect::<Vec<_>>();
+
+        let mut ring = CircularList::from(list);
+        assert_eq!(ring.len(), 4);
+        assert_eq!(ring.back().map(String::as_str), Some("3"));
+        ring.rotate_forward(2);
+
+        // Cut open at `current`, with the same nodes
+        let list = LinkedList::from(ring);
+        assert_eq!(list.len(), 4);
+        assert_eq!(list.front().map(String::as_str), Some("2"));
+        assert_eq!(list.back().map(String::as_str), Some("1"));
+        let after = list.iter().map(|s| s as *const String).collect::<Vec<_>>();
+        assert_eq!(after, [&addresses[2..], &addresses[..2]].concat());
+
+        let empty = CircularList::from(LinkedList::<i32>::new());
+        assert!(empty.is_empty());
+        assert!(LinkedList::from(empty).is_empty());
+    }
+}
@@ -133,7 +133,7 @@ impl<'a, T> CursorMut<'a, T> {
         } else {
             // We're at the ghost, just replace our list with an empty one.
             // No other state needs to be changed.
-            std::mem::replace(self.list, LinkedList::new())
+            std::mem::take(self.list)
         }
     }
     pub fn split_after(&mut self) -> LinkedList<T> {
@@ -178,7 +178,7 @@ impl<'a, T> CursorMut<'a, T> {
         } else {
             // We're at the ghost, just replace our list with an empty one.
             // No other state needs to be changed.
-            std::mem::replace(self.list, LinkedList::new())
+            std::mem::take(self.list)
         }
     }
     pub fn splice_before(&mut self, mut input: LinkedList<T>) {
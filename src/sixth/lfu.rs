@@ -0,0 +1,441 @@
+// A least-frequently-used cache with O(1) `get`, `put` and eviction.
+//
+// Entries are grouped into buckets by how often they've been used, and the
+// buckets sit in a `LinkedList` in increasing order of frequency. Each bucket
+// is a `LinkedList` of its entries, least recently used at the front. Using an
+// entry moves its node to the back of the next bucket up (making that bucket
+// if the next one isn't exactly one higher), so nothing ever gets searched:
+// the entry to evict is always the front of the front bucket.
+//
+// Nodes move between buckets by relinking, so a key's node stays put for as
+// long as it's in the cache and the map can point straight at it.
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::ptr::NonNull;
+
+use super::{LinkedList, Node};
+
+struct Bucket<K, V> {
+    freq: u64,
+    entries: LinkedList<Entry<K, V>>,
+}
+
+struct Entry<K, V> {
+    key: K,
+    value: V,
+    bucket: NonNull<Node<Bucket<K, V>>>,
+}
+
+pub struct LfuCache<K, V> {
+    buckets: LinkedList<Bucket<K, V>>,
+    map: HashMap<K, NonNull<Node<Entry<K, V>>>>,
+    capacity: usize,
+}
+
+impl<K: Hash + Eq + Clone, V> LfuCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        LfuCache {
+            buckets: LinkedList::new(),
+            map: HashMap::new(),
+            capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // Counts as a use.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node = *self.map.get(key)?;
+        unsafe {
+            self.touch(node);
+            Some(&(*node.as_ptr()).elem.value)
+        }
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node = *self.map.get(key)?;
+        unsafe {
+            self.touch(node);
+            Some(&mut (*node.as_ptr()).elem.value)
+        }
+    }
+
+    // Doesn't count as a use.
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node = self.map.get(key)?;
+        unsafe { Some(&(*node.as_ptr()).elem.value) }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.contains_key(key)
+    }
+
+    // How many times `key` has been used, counting the `put` that added it.
+    pub fn frequency<Q>(&self, key: &Q) -> Option<u64>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node = self.map.get(key)?;
+        unsafe { Some((*(*node.as_ptr()).elem.bucket.as_ptr()).elem.freq) }
+    }
+
+    // Replacing the value of a key that's already there counts as a use of
+    // it. Otherwise the new entry starts at frequency 1, evicting the least
+    // frequently used entry first if the cache is full; that entry (or the
+    // new one, if the capacity is 0) is returned.
+    pub fn put(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if let Some(&node) = self.map.get(&key) {
+            unsafe {
+                (*node.as_ptr()).elem.value = value;
+                self.touch(node);
+            }
+            return None;
+        }
+        if self.capacity == 0 {
+            return Some((key, value));
+        }
+        let evicted = if self.len() >= self.capacity {
+            self.pop_lfu()
+        } else {
+            None
+        };
+
+        unsafe {
+            let bucket = match self.buckets.front {
+                Some(bucket) if (*bucket.as_ptr()).elem.freq == 1 => bucket,
+                _ => self.new_bucket(None, 1),
+            };
+            let node = Node::alloc(Entry {
+                key: key.clone(),
+                value,
+                bucket,
+            });
+            let entries = &mut (*bucket.as_ptr()).elem.entries;
+            entries.link_after(entries.back, node);
+            self.map.insert(key, node);
+        }
+        evicted
+    }
+
+    // Removes the least frequently used entry, the least recently used one if
+    // there's a tie.
+    pub fn pop_lfu(&mut self) -> Option<(K, V)> {
+        let bucket = self.buckets.front?;
+        unsafe {
+            let node = (*bucket.as_ptr())
+                .elem
+                .entries
+                .front
+                .expect("buckets are never empty");
+            self.map.remove(&(*node.as_ptr()).elem.key);
+            let entry = self.unlink(node);
+            Some((entry.key, entry.value))
+        }
+    }
+
+    // The entry `pop_lfu` would remove.
+    pub fn peek_lfu(&self) -> Option<(&K, &V)> {
+        let entry = self.buckets.front()?.entries.front()?;
+        Some((&entry.key, &entry.value))
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node = self.map.remove(key)?;
+        unsafe { Some(self.unlink(node).value) }
+    }
+
+    // Shrinking evicts entries, least frequently used first, and returns them.
+    pub fn set_capacity(&mut self, capacity: usize) -> Vec<(K, V)> {
+        self.capacity = capacity;
+        let mut evicted = Vec::new();
+        while self.len() > capacity {
+            evicted.extend(self.pop_lfu());
+        }
+        evicted
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.buckets.clear();
+    }
+
+    // In eviction order: least frequently used first, ties least recently
+    // used first.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.buckets
+            .iter()
+            .flat_map(|bucket| bucket.entries.iter())
+            .map(|entry| (&entry.key, &entry.value))
+    }
+
+    // Bumps `node` into the next bucket up.
+    // SAFETY: `node` must be in the cache.
+    unsafe fn touch(&mut self, node: NonNull<Node<Entry<K, V>>>) {
+        let bucket = (*node.as_ptr()).elem.bucket;
+        let freq = (*bucket.as_ptr()).elem.freq;
+        let next = match (*bucket.as_ptr()).back {
+            Some(next) if (*next.as_ptr()).elem.freq == freq + 1 => next,
+            _ => self.new_bucket(Some(bucket), freq + 1),
+        };
+        (*bucket.as_ptr()).elem.entries.detach(node);
+        let entries = &mut (*next.as_ptr()).elem.entries;
+        entries.link_after(entries.back, node);
+        (*node.as_ptr()).elem.bucket = next;
+        self.drop_if_empty(bucket);
+    }
+
+    // Takes `node` out of its bucket and frees it. The caller deals with
+    // `map`.
+    // SAFETY: `node` must be in the cache.
+    unsafe fn unlink(&mut self, node: NonNull<Node<Entry<K, V>>>) -> Entry<K, V> {
+        let bucket = (*node.as_ptr()).elem.bucket;
+        let entry = (*bucket.as_ptr()).elem.entries.unlink(node);
+        self.drop_if_empty(bucket);
+        entry
+    }
+
+    // SAFETY: `after` must be in `buckets`.
+    unsafe fn new_bucket(
+        &mut self,
+        after: Option<NonNull<Node<Bucket<K, V>>>>,
+        freq: u64,
+    ) -> NonNull<Node<Bucket<K, V>>> {
+        let bucket = Node::alloc(Bucket {
+            freq,
+            entries: LinkedList::new(),
+        });
+        self.buckets.link_after(after, bucket);
+        bucket
+    }
+
+    // SAFETY: `bucket` must be in `buckets`.
+    unsafe fn drop_if_empty(&mut self, bucket: NonNull<Node<Bucket<K, V>>>) {
+        if (*bucket.as_ptr()).elem.entries.is_empty() {
+            self.buckets.unlink(bucket);
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone + fmt::Debug, V: fmt::Debug> fmt::Debug for LfuCache<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+// The map's pointers all point into `buckets`, which the cache owns.
+unsafe impl<K: Send, V: Send> Send for LfuCache<K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for LfuCache<K, V> {}
+
+#[cfg(test)]
+mod test {
+    use super::LfuCache;
+    use crate::test_util::{DropCounter, Lcg};
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn keys<V>(cache: &LfuCache<&'static str, V>) -> Vec<&'static str> {
+        cache.iter().map(|(k, _)| *k).collect()
+    }
+
+    #[test]
+    fn eviction_order() {
+        let mut cache = LfuCache::new(3);
+        assert_eq!(cache.put("a", 1), None);
+        assert_eq!(cache.put("b", 2), None);
+        assert_eq!(cache.put("c", 3), None);
+        // All used once: least recently used first
+        assert_eq!(keys(&cache), &["a", "b", "c"]);
+
+        assert_eq!(cache.get("a"), Some(&1));
+        assert_eq!(cache.get("a"), Some(&1));
+        assert_eq!(cache.get("b"), Some(&2));
+        assert_eq!(keys(&cache), &["c", "b", "a"]);
+        assert_eq!(cache.frequency("a"), Some(3));
+        assert_eq!(cache.frequency("c"), Some(1));
+
+        // "c" is the only one used once
+        assert_eq!(cache.put("d", 4), Some(("c", 3)));
+        // "d" now is
+        assert_eq!(cache.put("e", 5), Some(("d", 4)));
+        cache.get("e");
+        // "b" and "e" are both on 2, "b" was used longer ago
+        assert_eq!(cache.peek_lfu(), Some((&"b", &2)));
+        assert_eq!(cache.pop_lfu(), Some(("b", 2)));
+        assert_eq!(cache.pop_lfu(), Some(("e", 5)));
+        assert_eq!(cache.pop_lfu(), Some(("a", 1)));
+        assert_eq!(cache.pop_lfu(), None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn put_existing_and_peek() {
+        let mut cache = LfuCache::new(2);
+        cache.put("a", 1);
+        cache.put("b", 2);
+        // Updating counts as a use, peeking doesn't
+        assert_eq!(cache.put("a", 10), None);
+        assert_eq!(cache.peek("b"), Some(&2));
+        assert_eq!(cache.frequency("a"), Some(2));
+        assert_eq!(cache.frequency("b"), Some(1));
+        assert_eq!(cache.put("c", 3), Some(("b", 2)));
+        *cache.get_mut("c").unwrap() += 1;
+        assert_eq!(format!("{:?}", cache), r#"{"a": 10, "c": 4}"#);
+        assert!(!cache.contains_key("b"));
+        assert_eq!(cache.get("b"), None);
+    }
+
+    #[test]
+    fn remove() {
+        let mut cache = LfuCache::new(4);
+        for (i, key) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            cache.put(key, i);
+        }
+        cache.get("b");
+        assert_eq!(cache.remove("b"), Some(1));
+        assert_eq!(cache.remove("b"), None);
+        assert_eq!(cache.remove("a"), Some(0));
+        assert_eq!(keys(&cache), &["c", "d"]);
+        assert_eq!(cache.len(), 2);
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(cache.pop_lfu(), None);
+    }
+
+    #[test]
+    fn capacity_changes() {
+        let mut cache = LfuCache::new(4);
+        for (i, key) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            cache.put(key, i);
+        }
+        cache.get("a");
+        cache.get("c");
+        cache.get("c");
+
+        assert_eq!(cache.set_capacity(2), vec![("b", 1), ("d", 3)]);
+        assert_eq!(cache.capacity(), 2);
+        assert_eq!(keys(&cache), &["a", "c"]);
+
+        // Growing doesn't evict anything
+        assert!(cache.set_capacity(3).is_empty());
+        assert_eq!(cache.put("e", 4), None);
+        assert_eq!(cache.put("f", 5), Some(("e", 4)));
+
+        // Nothing fits in a zero-capacity cache
+        assert_eq!(cache.set_capacity(0).len(), 3);
+        assert_eq!(cache.put("g", 6), Some(("g", 6)));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn model() {
+        // The obvious O(n) version: evict the smallest (frequency, last use)
+        struct Model {
+            entries: HashMap<u32, (u32, u64, u64)>,
+            clock: u64,
+            capacity: usize,
+        }
+
+        impl Model {
+            fn touch(&mut self, key: u32) -> Option<u32> {
+                self.clock += 1;
+                let entry = self.entries.get_mut(&key)?;
+                entry.1 += 1;
+                entry.2 = self.clock;
+                Some(entry.0)
+            }
+
+            fn pop(&mut self) -> Option<(u32, u32)> {
+                let (&key, _) = self.entries.iter().min_by_key(|(_, e)| (e.1, e.2))?;
+                self.entries.remove(&key).map(|e| (key, e.0))
+            }
+
+            fn put(&mut self, key: u32, value: u32) -> Option<(u32, u32)> {
+                if self.touch(key).is_some() {
+                    self.entries.get_mut(&key).unwrap().0 = value;
+                    return None;
+                }
+                let evicted = if self.entries.len() >= self.capacity {
+                    self.pop()
+                } else {
+                    None
+                };
+                self.entries.insert(key, (value, 1, self.clock));
+                evicted
+            }
+        }
+
+        let mut rng = Lcg(17);
+        let mut cache = LfuCache::new(16);
+        let mut model = Model {
+            entries: HashMap::new(),
+            clock: 0,
+            capacity: 16,
+        };
+        for i in 0..10_000 {
+            let key = rng.next(40);
+            match rng.next(10) {
+                0..=3 => assert_eq!(cache.put(key, i), model.put(key, i)),
+                4..=7 => assert_eq!(cache.get(&key).copied(), model.touch(key)),
+                8 => assert_eq!(cache.remove(&key), model.entries.remove(&key).map(|e| e.0)),
+                _ => assert_eq!(cache.pop_lfu(), model.pop()),
+            }
+            assert_eq!(cache.len(), model.entries.len());
+        }
+        let mut order: Vec<_> = model.entries.iter().collect();
+        order.sort_by_key(|(_, e)| (e.1, e.2));
+        assert!(cache
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .eq(order.iter().map(|(k, e)| (**k, e.0))));
+    }
+
+    #[test]
+    fn drops_everything_once() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let mut cache = LfuCache::new(3);
+        for i in 0..5 {
+            cache.put(i, DropCounter(drops.clone()));
+            cache.get(&i);
+        }
+        // Two evicted, and dropped by us
+        assert_eq!(drops.load(Ordering::SeqCst), 2);
+        drop(cache.remove(&4));
+        assert_eq!(drops.load(Ordering::SeqCst), 3);
+        drop(cache);
+        assert_eq!(drops.load(Ordering::SeqCst), 5);
+    }
+}
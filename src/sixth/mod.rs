@@ -4,10 +4,15 @@ use std::fmt::Debug;
 use std::hash::Hash;
 use std::hash::Hasher;
 use std::marker::PhantomData;
-use std::ptr::NonNull;
+use std::ptr::{self, NonNull};
 
+pub mod circular;
 mod cursor;
 mod iterator;
+pub mod lfu;
+pub mod multi;
+pub mod sorted;
+pub mod timer;
 pub type Link<T> = Option<NonNull<Node<T>>>;
 
 pub struct Node<T> {
@@ -16,6 +21,80 @@ pub struct Node<T> {
     elem: T,
 }
 
+impl<T> Node<T> {
+    // A boxed node that isn't linked to anything yet. `circular` builds its
+    // ring out of these too.
+    fn alloc(elem: T) -> NonNull<Node<T>> {
+        let node = Box::new(Node {
+            front: None,
+            back: None,
+            elem,
+        });
+        // SAFETY: boxes are never null
+        unsafe { NonNull::new_unchecked(Box::into_raw(node)) }
+    }
+
+    // SAFETY: `node` must be live.
+    unsafe fn pair(node: NonNull<Node<T>>) -> Pair<Node<T>> {
+        let node = node.as_ptr();
+        (
+            ptr::addr_of_mut!((*node).front),
+            ptr::addr_of_mut!((*node).back),
+        )
+    }
+}
+
+// Where a node (or a list) keeps its `front` and `back` links. Raw pointers,
+// so `multi` can hand out a different pair of the same node for each list
+// it's on.
+type Pair<N> = (*mut Option<NonNull<N>>, *mut Option<NonNull<N>>);
+
+// The pointer surgery behind `LinkedList::link_after`, for any node type:
+// `ends` is the list's front/back and `pair` finds a node's. Links `new` in
+// right after `at`, or at the very front for `None`. Lengths are up to the
+// caller.
+// SAFETY: `at` must be on the list, `new` must be live and not on it.
+unsafe fn link_pair_after<N>(
+    ends: Pair<N>,
+    pair: impl Fn(NonNull<N>) -> Pair<N>,
+    at: Option<NonNull<N>>,
+    new: NonNull<N>,
+) {
+    let (front, back) = ends;
+    let next = match at {
+        Some(at) => *pair(at).1,
+        None => *front,
+    };
+    let (new_front, new_back) = pair(new);
+    *new_front = at;
+    *new_back = next;
+    match at {
+        Some(at) => *pair(at).1 = Some(new),
+        None => *front = Some(new),
+    }
+    match next {
+        Some(next) => *pair(next).0 = Some(new),
+        None => *back = Some(new),
+    }
+}
+
+// The other half, behind `LinkedList::detach`: takes `node` out and clears its
+// pair, without freeing it.
+// SAFETY: `node` must be on the list.
+unsafe fn detach_pair<N>(ends: Pair<N>, pair: impl Fn(NonNull<N>) -> Pair<N>, node: NonNull<N>) {
+    let (front, back) = ends;
+    let (node_front, node_back) = pair(node);
+    let (prev, next) = (node_front.replace(None), node_back.replace(None));
+    match prev {
+        Some(prev) => *pair(prev).1 = next,
+        None => *front = next,
+    }
+    match next {
+        Some(next) => *pair(next).0 = prev,
+        None => *back = prev,
+    }
+}
+
 pub struct LinkedList<T> {
     front: Link<T>,
     back: Link<T>,
@@ -34,11 +113,7 @@ impl<T> LinkedList<T> {
     }
     pub fn push_front(&mut self, elem: T) {
         unsafe {
-            let new = NonNull::new_unchecked(Box::into_raw(Box::new(Node {
-                front: None,
-                back: None,
-                elem,
-            })));
+            let new = Node::alloc(elem);
             if let Some(old) = self.front {
                 // Put the new front before the old one
                 (*old.as_ptr()).front = Some(new);
@@ -55,11 +130,7 @@ impl<T> LinkedList<T> {
     pub fn push_back(&mut self, elem: T) {
         // SAFETY: it's a linked-list, what do you want?
         unsafe {
-            let new = NonNull::new_unchecked(Box::into_raw(Box::new(Node {
-                back: None,
-                front: None,
-                elem,
-            })));
+            let new = Node::alloc(elem);
             if let Some(old) = self.back {
                 // Put the new back before the old one
                 (*old.as_ptr()).back = Some(new);
@@ -152,6 +223,32 @@ impl<T> LinkedList<T> {
         // Oh look it's drop again
         while self.pop_front().is_some() {}
     }
+
+    // Links `new` in right after `at`, or at the very front for `None`.
+    // SAFETY: `at` must be in this list, `new` must be unlinked.
+    unsafe fn link_after(&mut self, at: Link<T>, new: NonNull<Node<T>>) {
+        link_pair_after(self.ends(), |node| Node::pair(node), at, new);
+        self.len += 1;
+    }
+
+    // Takes `node` out of the list without freeing it, so it can be linked
+    // into another one.
+    // SAFETY: `node` must be in this list.
+    unsafe fn detach(&mut self, node: NonNull<Node<T>>) {
+        detach_pair(self.ends(), |node| Node::pair(node), node);
+        self.len -= 1;
+    }
+
+    fn ends(&mut self) -> Pair<Node<T>> {
+        (&mut self.front, &mut self.back)
+    }
+
+    // Takes `node` out of the list and frees it.
+    // SAFETY: `node` must be in this list.
+    unsafe fn unlink(&mut self, node: NonNull<Node<T>>) -> T {
+        self.detach(node);
+        Box::from_raw(node.as_ptr()).elem
+    }
 }
 
 // Other traits
@@ -212,6 +309,9 @@ impl<T: Hash> Hash for LinkedList<T> {
     }
 }
 
+unsafe impl<T: Send> Send for LinkedList<T> {}
+unsafe impl<T: Sync> Sync for LinkedList<T> {}
+
 #[cfg(test)]
 mod test {
     use super::LinkedList;
@@ -326,8 +426,9 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::neg_cmp_op_on_partial_ord)]
     fn test_ord_nan() {
-        let nan = 0.0f64 / 0.0;
+        let nan = f64::NAN;
         let n = list_from(&[nan]);
         let m = list_from(&[nan]);
         assert!(!(n < m));
@@ -362,10 +463,7 @@ mod test {
         let list: LinkedList<i32> = (0..10).collect();
         assert_eq!(format!("{:?}", list), "[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]");
 
-        let list: LinkedList<&str> = vec!["just", "one", "test", "more"]
-            .iter()
-            .copied()
-            .collect();
+        let list: LinkedList<&str> = ["just", "one", "test", "more"].iter().copied().collect();
         assert_eq!(format!("{:?}", list), r#"["just", "one", "test", "more"]"#);
     }
 
@@ -391,6 +489,3 @@ mod test {
         assert!(map.is_empty());
     }
 }
-
-unsafe impl<T: Send> Send for LinkedList<T> {}
-unsafe impl<T: Sync> Sync for LinkedList<T> {}
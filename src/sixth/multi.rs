@@ -0,0 +1,818 @@
+// One set of elements threaded onto `N` lists at once, like an "all items"
+// list plus a "dirty" list, where taking an element off one or all of them is
+// O(1).
+//
+// Every node carries `N` front/back pairs, one per list, so the same node is
+// linked into each list directly instead of each list holding a pointer to a
+// shared element. The linking itself is the same `link_pair_after` and
+// `detach_pair` that `LinkedList` uses, just handed the pair for one list
+// index.
+//
+// The `MultiList` owns the elements, whether they're on any list or not.
+// `insert` hands out a `MultiHandle` (checked against a map, like
+// `TimerHandle`, so a stale one is just ignored), and the per-list views link
+// and unlink elements by handle.
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ptr::{self, NonNull};
+
+use super::{detach_pair, link_pair_after, Pair};
+
+type Link<T, const N: usize> = Option<NonNull<MultiNode<T, N>>>;
+
+struct Links<T, const N: usize> {
+    front: Link<T, N>,
+    back: Link<T, N>,
+    // A lone node has no neighbours either, so this says whether it's on the
+    // list at all
+    linked: bool,
+}
+
+struct MultiNode<T, const N: usize> {
+    links: [Links<T, N>; N],
+    id: u64,
+    elem: T,
+}
+
+struct Ends<T, const N: usize> {
+    front: Link<T, N>,
+    back: Link<T, N>,
+    len: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MultiHandle(u64);
+
+pub struct MultiList<T, const N: usize> {
+    lists: [Ends<T, N>; N],
+    nodes: HashMap<u64, NonNull<MultiNode<T, N>>>,
+    next_id: u64,
+    _boo: PhantomData<T>,
+}
+
+impl<T, const N: usize> MultiList<T, N> {
+    pub fn new() -> Self {
+        MultiList {
+            lists: std::array::from_fn(|_| Ends {
+                front: None,
+                back: None,
+                len: 0,
+            }),
+            nodes: HashMap::new(),
+            next_id: 0,
+            _boo: PhantomData,
+        }
+    }
+
+    // How many elements there are, on any list or none.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    // Adds `elem` without putting it on any list yet.
+    pub fn insert(&mut self, elem: T) -> MultiHandle {
+        let id = self.next_id;
+        self.next_id += 1;
+        let node = Box::new(MultiNode {
+            links: std::array::from_fn(|_| Links {
+                front: None,
+                back: None,
+                linked: false,
+            }),
+            id,
+            elem,
+        });
+        // SAFETY: boxes are never null
+        let node = unsafe { NonNull::new_unchecked(Box::into_raw(node)) };
+        self.nodes.insert(id, node);
+        MultiHandle(id)
+    }
+
+    pub fn contains(&self, handle: MultiHandle) -> bool {
+        self.nodes.contains_key(&handle.0)
+    }
+
+    pub fn get(&self, handle: MultiHandle) -> Option<&T> {
+        let node = self.nodes.get(&handle.0)?;
+        unsafe { Some(&(*node.as_ptr()).elem) }
+    }
+
+    pub fn get_mut(&mut self, handle: MultiHandle) -> Option<&mut T> {
+        let node = self.nodes.get(&handle.0)?;
+        unsafe { Some(&mut (*node.as_ptr()).elem) }
+    }
+
+    // Takes the element off every list it's on and hands it back.
+    pub fn remove(&mut self, handle: MultiHandle) -> Option<T> {
+        let node = *self.nodes.get(&handle.0)?;
+        unsafe { Some(self.free(node)) }
+    }
+
+    pub fn clear(&mut self) {
+        for (_, node) in self.nodes.drain() {
+            drop(unsafe { Box::from_raw(node.as_ptr()) });
+        }
+        for list in &mut self.lists {
+            *list = Ends {
+                front: None,
+                back: None,
+                len: 0,
+            };
+        }
+    }
+
+    pub fn view(&self, index: usize) -> View<'_, T, N> {
+        assert!(
+            index < N,
+            "list index {} out of range for {} lists",
+            index,
+            N
+        );
+        View { list: self, index }
+    }
+
+    pub fn view_mut(&mut self, index: usize) -> ViewMut<'_, T, N> {
+        assert!(
+            index < N,
+            "list index {} out of range for {} lists",
+            index,
+            N
+        );
+        ViewMut { list: self, index }
+    }
+
+    fn node(&self, handle: MultiHandle) -> Option<NonNull<MultiNode<T, N>>> {
+        self.nodes.get(&handle.0).copied()
+    }
+
+    // SAFETY: `node` must be one of ours.
+    unsafe fn free(&mut self, node: NonNull<MultiNode<T, N>>) -> T {
+        for index in 0..N {
+            if (*node.as_ptr()).links[index].linked {
+                self.detach(index, node);
+            }
+        }
+        self.nodes.remove(&(*node.as_ptr()).id);
+        Box::from_raw(node.as_ptr()).elem
+    }
+
+    // `LinkedList::link_after` on list `index`: links `new` right after `at`,
+    // or at the very front for `None`.
+    // SAFETY: `at` must be on list `index`, `new` must be ours and not on it.
+    unsafe fn link_after(&mut self, index: usize, at: Link<T, N>, new: NonNull<MultiNode<T, N>>) {
+        let ends = &mut self.lists[index];
+        link_pair_after(
+            (&mut ends.front, &mut ends.back),
+            |node| MultiNode::pair(node, index),
+            at,
+            new,
+        );
+        (*new.as_ptr()).links[index].linked = true;
+        ends.len += 1;
+    }
+
+    // `LinkedList::detach` on list `index`.
+    // SAFETY: `node` must be on list `index`.
+    unsafe fn detach(&mut self, index: usize, node: NonNull<MultiNode<T, N>>) {
+        let ends = &mut self.lists[index];
+        detach_pair(
+            (&mut ends.front, &mut ends.back),
+            |node| MultiNode::pair(node, index),
+            node,
+        );
+        (*node.as_ptr()).links[index].linked = false;
+        ends.len -= 1;
+    }
+}
+
+impl<T, const N: usize> MultiNode<T, N> {
+    // The front/back pair for list `index`.
+    // SAFETY: `node` must be live.
+    unsafe fn pair(node: NonNull<Self>, index: usize) -> Pair<Self> {
+        let links = ptr::addr_of_mut!((*node.as_ptr()).links[index]);
+        (
+            ptr::addr_of_mut!((*links).front),
+            ptr::addr_of_mut!((*links).back),
+        )
+    }
+}
+
+impl<T, const N: usize> Drop for MultiList<T, N> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T, const N: usize> Default for MultiList<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Prints each list in turn.
+impl<T: fmt::Debug, const N: usize> fmt::Debug for MultiList<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries((0..N).map(|index| self.view(index)))
+            .finish()
+    }
+}
+
+unsafe impl<T: Send, const N: usize> Send for MultiList<T, N> {}
+unsafe impl<T: Sync, const N: usize> Sync for MultiList<T, N> {}
+
+// List `index` of a `MultiList`, read-only.
+pub struct View<'a, T, const N: usize> {
+    list: &'a MultiList<T, N>,
+    index: usize,
+}
+
+// List `index` of a `MultiList`. Linking and unlinking only touch this list;
+// elements stay in the `MultiList` until it's told to `remove` them.
+pub struct ViewMut<'a, T, const N: usize> {
+    list: &'a mut MultiList<T, N>,
+    index: usize,
+}
+
+impl<'a, T, const N: usize> View<'a, T, N> {
+    pub fn len(&self) -> usize {
+        self.list.lists[self.index].len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, handle: MultiHandle) -> bool {
+        self.list
+            .node(handle)
+            .is_some_and(|node| unsafe { (*node.as_ptr()).links[self.index].linked })
+    }
+
+    pub fn front(&self) -> Option<&'a T> {
+        unsafe { Some(&(*self.list.lists[self.index].front?.as_ptr()).elem) }
+    }
+
+    pub fn back(&self) -> Option<&'a T> {
+        unsafe { Some(&(*self.list.lists[self.index].back?.as_ptr()).elem) }
+    }
+
+    pub fn iter(&self) -> Iter<'a, T, N> {
+        let ends = &self.list.lists[self.index];
+        Iter {
+            front: ends.front,
+            back: ends.back,
+            len: ends.len,
+            index: self.index,
+            _boo: PhantomData,
+        }
+    }
+}
+
+impl<'a, T, const N: usize> ViewMut<'a, T, N> {
+    pub fn len(&self) -> usize {
+        self.list.lists[self.index].len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, handle: MultiHandle) -> bool {
+        View {
+            list: self.list,
+            index: self.index,
+        }
+        .contains(handle)
+    }
+
+    // Puts the element on the back of this list. `false` if it's already on
+    // it, or the handle is stale.
+    pub fn push_back(&mut self, handle: MultiHandle) -> bool {
+        let back = self.list.lists[self.index].back;
+        self.link_after(back, handle)
+    }
+
+    pub fn push_front(&mut self, handle: MultiHandle) -> bool {
+        self.link_after(None, handle)
+    }
+
+    // Takes the element off this list only. `false` if it wasn't on it.
+    pub fn remove(&mut self, handle: MultiHandle) -> bool {
+        if !self.contains(handle) {
+            return false;
+        }
+        let node = self.list.node(handle).unwrap();
+        unsafe { self.list.detach(self.index, node) };
+        true
+    }
+
+    pub fn pop_front(&mut self) -> Option<MultiHandle> {
+        let node = self.list.lists[self.index].front?;
+        unsafe {
+            self.list.detach(self.index, node);
+            Some(MultiHandle((*node.as_ptr()).id))
+        }
+    }
+
+    pub fn pop_back(&mut self) -> Option<MultiHandle> {
+        let node = self.list.lists[self.index].back?;
+        unsafe {
+            self.list.detach(self.index, node);
+            Some(MultiHandle((*node.as_ptr()).id))
+        }
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        unsafe { Some(&mut (*self.list.lists[self.index].front?.as_ptr()).elem) }
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        unsafe { Some(&mut (*self.list.lists[self.index].back?.as_ptr()).elem) }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T, N> {
+        View {
+            list: self.list,
+            index: self.index,
+        }
+        .iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, N> {
+        let ends = &self.list.lists[self.index];
+        IterMut {
+            front: ends.front,
+            back: ends.back,
+            len: ends.len,
+            index: self.index,
+            _boo: PhantomData,
+        }
+    }
+
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T, N> {
+        CursorMut {
+            cur: None,
+            list: self.list,
+            list_index: self.index,
+            index: None,
+        }
+    }
+
+    fn link_after(&mut self, at: Link<T, N>, handle: MultiHandle) -> bool {
+        if self.contains(handle) {
+            return false;
+        }
+        match self.list.node(handle) {
+            Some(node) => {
+                unsafe { self.list.link_after(self.index, at, node) };
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<'a, T: fmt::Debug, const N: usize> fmt::Debug for View<'a, T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+pub struct Iter<'a, T, const N: usize> {
+    front: Link<T, N>,
+    back: Link<T, N>,
+    len: usize,
+    index: usize,
+    _boo: PhantomData<&'a T>,
+}
+
+pub struct IterMut<'a, T, const N: usize> {
+    front: Link<T, N>,
+    back: Link<T, N>,
+    len: usize,
+    index: usize,
+    _boo: PhantomData<&'a mut T>,
+}
+
+impl<'a, T, const N: usize> Iterator for Iter<'a, T, N> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.front.map(|node| unsafe {
+            self.len -= 1;
+            self.front = (*node.as_ptr()).links[self.index].back;
+            &(*node.as_ptr()).elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T, const N: usize> DoubleEndedIterator for Iter<'a, T, N> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.back.map(|node| unsafe {
+            self.len -= 1;
+            self.back = (*node.as_ptr()).links[self.index].front;
+            &(*node.as_ptr()).elem
+        })
+    }
+}
+
+impl<'a, T, const N: usize> ExactSizeIterator for Iter<'a, T, N> {}
+
+impl<'a, T, const N: usize> Iterator for IterMut<'a, T, N> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.front.map(|node| unsafe {
+            self.len -= 1;
+            self.front = (*node.as_ptr()).links[self.index].back;
+            &mut (*node.as_ptr()).elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T, const N: usize> DoubleEndedIterator for IterMut<'a, T, N> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.back.map(|node| unsafe {
+            self.len -= 1;
+            self.back = (*node.as_ptr()).links[self.index].front;
+            &mut (*node.as_ptr()).elem
+        })
+    }
+}
+
+impl<'a, T, const N: usize> ExactSizeIterator for IterMut<'a, T, N> {}
+
+unsafe impl<'a, T: Sync, const N: usize> Send for Iter<'a, T, N> {}
+unsafe impl<'a, T: Sync, const N: usize> Sync for Iter<'a, T, N> {}
+unsafe impl<'a, T: Send, const N: usize> Send for IterMut<'a, T, N> {}
+unsafe impl<'a, T: Sync, const N: usize> Sync for IterMut<'a, T, N> {}
+
+// Like `LinkedList`'s cursor, on one of the lists: there's a ghost position
+// between the back and the front, where `index` is `None`.
+pub struct CursorMut<'a, T, const N: usize> {
+    cur: Link<T, N>,
+    list: &'a mut MultiList<T, N>,
+    list_index: usize,
+    index: Option<usize>,
+}
+
+impl<'a, T, const N: usize> CursorMut<'a, T, N> {
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    pub fn move_next(&mut self) {
+        let ends = &self.list.lists[self.list_index];
+        match self.cur {
+            Some(cur) => {
+                self.cur = unsafe { (*cur.as_ptr()).links[self.list_index].back };
+                self.index = self.cur.and(self.index.map(|i| i + 1));
+            }
+            None if ends.len > 0 => {
+                self.cur = ends.front;
+                self.index = Some(0);
+            }
+            None => {}
+        }
+    }
+
+    pub fn move_prev(&mut self) {
+        let ends = &self.list.lists[self.list_index];
+        match self.cur {
+            Some(cur) => {
+                self.cur = unsafe { (*cur.as_ptr()).links[self.list_index].front };
+                self.index = self.cur.and_then(|_| self.index.map(|i| i - 1));
+            }
+            None if ends.len > 0 => {
+                self.cur = ends.back;
+                self.index = Some(ends.len - 1);
+            }
+            None => {}
+        }
+    }
+
+    pub fn current(&mut self) -> Option<&mut T> {
+        unsafe { self.cur.map(|node| &mut (*node.as_ptr()).elem) }
+    }
+
+    pub fn handle(&self) -> Option<MultiHandle> {
+        unsafe { self.cur.map(|node| MultiHandle((*node.as_ptr()).id)) }
+    }
+
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        let next = match self.cur {
+            Some(cur) => unsafe { (*cur.as_ptr()).links[self.list_index].back },
+            None => self.list.lists[self.list_index].front,
+        };
+        unsafe { next.map(|node| &mut (*node.as_ptr()).elem) }
+    }
+
+    pub fn peek_prev(&mut self) -> Option<&mut T> {
+        let prev = match self.cur {
+            Some(cur) => unsafe { (*cur.as_ptr()).links[self.list_index].front },
+            None => self.list.lists[self.list_index].back,
+        };
+        unsafe { prev.map(|node| &mut (*node.as_ptr()).elem) }
+    }
+
+    // Links the element in after the current one; at the ghost that's the
+    // front. `false` if it's already on this list or the handle is stale.
+    pub fn insert_after(&mut self, handle: MultiHandle) -> bool {
+        let mut view = ViewMut {
+            list: self.list,
+            index: self.list_index,
+        };
+        view.link_after(self.cur, handle)
+    }
+
+    // Links the element in before the current one; at the ghost that's the
+    // back.
+    pub fn insert_before(&mut self, handle: MultiHandle) -> bool {
+        let prev = match self.cur {
+            Some(cur) => unsafe { (*cur.as_ptr()).links[self.list_index].front },
+            None => self.list.lists[self.list_index].back,
+        };
+        let mut view = ViewMut {
+            list: self.list,
+            index: self.list_index,
+        };
+        let linked = view.link_after(prev, handle);
+        if linked {
+            self.index = self.index.map(|i| i + 1);
+        }
+        linked
+    }
+
+    // Takes the current element off this list and moves to the next one.
+    pub fn remove_current(&mut self) -> Option<MultiHandle> {
+        let node = self.cur?;
+        unsafe {
+            self.cur = (*node.as_ptr()).links[self.list_index].back;
+            if self.cur.is_none() {
+                self.index = None;
+            }
+            self.list.detach(self.list_index, node);
+            Some(MultiHandle((*node.as_ptr()).id))
+        }
+    }
+
+    // Takes the current element off every list and out of the `MultiList`,
+    // then moves to the next one.
+    pub fn take_current(&mut self) -> Option<T> {
+        let node = self.cur?;
+        unsafe {
+            self.cur = (*node.as_ptr()).links[self.list_index].back;
+            if self.cur.is_none() {
+                self.index = None;
+            }
+            Some(self.list.free(node))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MultiList;
+    use crate::test_util::{DropCounter, Lcg};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const ALL: usize = 0;
+    const EVEN: usize = 1;
+    const DIRTY: usize = 2;
+
+    fn contents<const N: usize>(list: &MultiList<i32, N>, index: usize) -> Vec<i32> {
+        list.view(index).iter().copied().collect()
+    }
+
+    #[test]
+    fn basics() {
+        let mut list = MultiList::<i32, 3>::new();
+        let handles: Vec<_> = (0..6).map(|i| list.insert(i)).collect();
+        assert_eq!(list.len(), 6);
+        assert!(list.view(ALL).is_empty());
+
+        for (i, &h) in handles.iter().enumerate() {
+            assert!(list.view_mut(ALL).push_back(h));
+            if i % 2 == 0 {
+                assert!(list.view_mut(EVEN).push_front(h));
+            }
+        }
+        // Already on it
+        assert!(!list.view_mut(ALL).push_back(handles[0]));
+        assert_eq!(contents(&list, ALL), &[0, 1, 2, 3, 4, 5]);
+        assert_eq!(contents(&list, EVEN), &[4, 2, 0]);
+        assert_eq!(list.view(EVEN).len(), 3);
+        assert_eq!(list.view(EVEN).back(), Some(&0));
+        assert!(list.view(EVEN).contains(handles[2]));
+        assert!(!list.view(EVEN).contains(handles[3]));
+
+        // Off one list only
+        assert!(list.view_mut(EVEN).remove(handles[2]));
+        assert!(!list.view_mut(EVEN).remove(handles[2]));
+        assert_eq!(contents(&list, EVEN), &[4, 0]);
+        assert_eq!(contents(&list, ALL), &[0, 1, 2, 3, 4, 5]);
+
+        // Off every list
+        assert_eq!(list.remove(handles[4]), Some(4));
+        assert_eq!(list.remove(handles[4]), None);
+        assert_eq!(contents(&list, EVEN), &[0]);
+        assert_eq!(contents(&list, ALL), &[0, 1, 2, 3, 5]);
+        assert_eq!(list.len(), 5);
+
+        // Stale handles are ignored
+        assert!(!list.view_mut(DIRTY).push_back(handles[4]));
+        assert_eq!(list.get(handles[4]), None);
+        *list.get_mut(handles[5]).unwrap() = 50;
+        assert_eq!(format!("{:?}", list), "[[0, 1, 2, 3, 50], [0], []]");
+    }
+
+    #[test]
+    fn ends_and_iterators() {
+        let mut list = MultiList::<i32, 2>::new();
+        for i in 0..5 {
+            let h = list.insert(i);
+            list.view_mut(0).push_back(h);
+            list.view_mut(1).push_front(h);
+        }
+        assert_eq!(
+            list.view(0).iter().rev().copied().collect::<Vec<_>>(),
+            &[4, 3, 2, 1, 0]
+        );
+        for x in list.view_mut(1).iter_mut().take(2) {
+            *x *= 10;
+        }
+        assert_eq!(contents(&list, 0), &[0, 1, 2, 30, 40]);
+        assert_eq!(list.view(1).iter().len(), 5);
+
+        let first = list.view_mut(0).pop_front().unwrap();
+        let last = list.view_mut(0).pop_back().unwrap();
+        assert_eq!(list.get(first), Some(&0));
+        assert_eq!(list.get(last), Some(&40));
+        assert_eq!(contents(&list, 0), &[1, 2, 30]);
+        // Still on the other one
+        assert_eq!(contents(&list, 1), &[40, 30, 2, 1, 0]);
+        *list.view_mut(1).front_mut().unwrap() += 1;
+        *list.view_mut(1).back_mut().unwrap() += 1;
+        assert_eq!(contents(&list, 1), &[41, 30, 2, 1, 1]);
+    }
+
+    #[test]
+    fn cursor() {
+        let mut list = MultiList::<i32, 2>::new();
+        let handles: Vec<_> = (0..5).map(|i| list.insert(i)).collect();
+        for &h in &handles {
+            list.view_mut(0).push_back(h);
+        }
+        list.view_mut(1).push_back(handles[2]);
+
+        let mut view = list.view_mut(1);
+        let mut cursor = view.cursor_mut();
+        assert_eq!(cursor.index(), None);
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&mut 2));
+        assert_eq!(cursor.handle(), Some(handles[2]));
+        assert!(cursor.insert_after(handles[3]));
+        assert!(cursor.insert_before(handles[1]));
+        assert!(!cursor.insert_before(handles[3]));
+        assert_eq!(cursor.index(), Some(1));
+        assert_eq!(cursor.peek_prev(), Some(&mut 1));
+        assert_eq!(cursor.peek_next(), Some(&mut 3));
+
+        // At the ghost: before is the back, after is the front
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.index(), None);
+        assert!(cursor.insert_before(handles[4]));
+        assert!(cursor.insert_after(handles[0]));
+        cursor.move_prev();
+        assert_eq!(cursor.index(), Some(4));
+        assert_eq!(cursor.current(), Some(&mut 4));
+        assert_eq!(contents(&list, 1), &[0, 1, 2, 3, 4]);
+
+        let mut view = list.view_mut(1);
+        let mut cursor = view.cursor_mut();
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), Some(handles[1]));
+        assert_eq!(cursor.current(), Some(&mut 2));
+        assert_eq!(cursor.take_current(), Some(2));
+        assert_eq!(cursor.index(), Some(1));
+        assert_eq!(cursor.current(), Some(&mut 3));
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.take_current(), None);
+
+        assert_eq!(contents(&list, 1), &[0, 3, 4]);
+        // `take_current` took it off list 0 too, `remove_current` didn't
+        assert_eq!(contents(&list, 0), &[0, 1, 3, 4]);
+        assert_eq!(list.len(), 4);
+    }
+
+    #[test]
+    fn model() {
+        // Each list modelled as a Vec of ids
+        let mut rng = Lcg(19);
+        let mut list = MultiList::<usize, 3>::new();
+        let mut handles = Vec::new();
+        let mut model: [Vec<usize>; 3] = Default::default();
+        for i in 0..4_000 {
+            let index = rng.next(3);
+            match rng.next(6) {
+                0 => handles.push(Some(list.insert(handles.len()))),
+                1 | 2 if !handles.is_empty() => {
+                    let id = rng.next(handles.len());
+                    let pushed = handles[id].is_some_and(|h| list.view_mut(index).push_back(h));
+                    let expected = handles[id].is_some() && !model[index].contains(&id);
+                    assert_eq!(pushed, expected);
+                    if expected {
+                        model[index].push(id);
+                    }
+                }
+                3 if !handles.is_empty() => {
+                    let id = rng.next(handles.len());
+                    let removed = handles[id].is_some_and(|h| list.view_mut(index).remove(h));
+                    let at = model[index].iter().position(|&x| x == id);
+                    assert_eq!(removed, at.is_some());
+                    if let Some(at) = at {
+                        model[index].remove(at);
+                    }
+                }
+                4 if !handles.is_empty() && i % 3 == 0 => {
+                    let id = rng.next(handles.len());
+                    if let Some(h) = handles[id].take() {
+                        assert_eq!(list.remove(h), Some(id));
+                        for ids in &mut model {
+                            ids.retain(|&x| x != id);
+                        }
+                    }
+                }
+                _ => {
+                    let mut view = list.view_mut(index);
+                    if rng.next(2) == 0 {
+                        assert_eq!(
+                            view.pop_front().map(|h| *list.get(h).unwrap()),
+                            first(&mut model[index])
+                        );
+                    } else {
+                        assert_eq!(
+                            view.pop_back().map(|h| *list.get(h).unwrap()),
+                            model[index].pop()
+                        );
+                    }
+                }
+            }
+            for (index, ids) in model.iter().enumerate() {
+                assert!(list.view(index).iter().eq(ids.iter()));
+                assert!(list.view(index).iter().rev().eq(ids.iter().rev()));
+            }
+        }
+
+        fn first(ids: &mut Vec<usize>) -> Option<usize> {
+            (!ids.is_empty()).then(|| ids.remove(0))
+        }
+    }
+
+    #[test]
+    fn drops_everything_once() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let mut list = MultiList::<_, 2>::new();
+        let a = list.insert(DropCounter(drops.clone()));
+        let b = list.insert(DropCounter(drops.clone()));
+        let _unlinked = list.insert(DropCounter(drops.clone()));
+        list.view_mut(0).push_back(a);
+        list.view_mut(1).push_back(a);
+        list.view_mut(1).push_back(b);
+        drop(list.remove(b));
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        // `a` is on two lists and `_unlinked` on none, each dropped once
+        drop(list);
+        assert_eq!(drops.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,579 @@
+// A `LinkedList` that keeps itself sorted, for the timer and priority lists that
+// used to get re-sorted after every push.
+//
+// Inserts are stable: a new element goes after everything that compares equal
+// to it, so equal elements come out in the order they went in. The insert
+// position is searched for from both ends at once, which finds it in about
+// twice the distance from whichever end is nearer. Pushing the new largest (or
+// smallest) element, the usual case for timers, is O(1).
+//
+// The order comes from a `Compare`, which is `Natural` (plain `Ord`) by default
+// and can be any `Fn(&T, &T) -> Ordering`.
+use std::cmp::Ordering;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+use std::ptr::NonNull;
+
+use super::{Link, LinkedList, Node};
+
+pub trait Compare<T: ?Sized> {
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+// `Ord`'s order.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Natural;
+
+impl<T: Ord + ?Sized> Compare<T> for Natural {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+impl<T: ?Sized, F: Fn(&T, &T) -> Ordering> Compare<T> for F {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self(a, b)
+    }
+}
+
+pub struct SortedList<T, C = Natural> {
+    list: LinkedList<T>,
+    cmp: C,
+}
+
+impl<T: Ord> SortedList<T> {
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+}
+
+impl<T, C> SortedList<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        SortedList {
+            list: LinkedList::new(),
+            cmp,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    // The smallest element, the first of them if there are several.
+    pub fn first(&self) -> Option<&T> {
+        self.list.front()
+    }
+
+    // The largest element, the last of them if there are several.
+    pub fn last(&self) -> Option<&T> {
+        self.list.back()
+    }
+
+    pub fn pop_first(&mut self) -> Option<T> {
+        self.list.pop_front()
+    }
+
+    pub fn pop_last(&mut self) -> Option<T> {
+        self.list.pop_back()
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear()
+    }
+
+    // Only shared access: changing an element in place could break the order.
+    pub fn iter(&self) -> super::iterator::Iter<'_, T> {
+        self.list.iter()
+    }
+
+    pub fn as_list(&self) -> &LinkedList<T> {
+        &self.list
+    }
+
+    pub fn into_list(self) -> LinkedList<T> {
+        self.list
+    }
+
+    unsafe fn elem<'a>(node: NonNull<Node<T>>) -> &'a T {
+        &(*node.as_ptr()).elem
+    }
+}
+
+impl<T, C: Compare<T>> SortedList<T, C> {
+    pub fn insert(&mut self, elem: T) {
+        let new = Node::alloc(elem);
+        unsafe {
+            let at = self.insert_point(Self::elem(new));
+            self.list.link_after(at, new);
+        }
+    }
+
+    // Removes the first element equal to `elem`.
+    pub fn remove(&mut self, elem: &T) -> Option<T> {
+        let node = self.find(elem)?;
+        unsafe { Some(self.list.unlink(node)) }
+    }
+
+    pub fn contains(&self, elem: &T) -> bool {
+        self.find(elem).is_some()
+    }
+
+    // Moves every element of `other` in, in O(n + m), without allocating:
+    // the two chains of nodes get zipped together. Elements of `self` go
+    // before equal elements of `other`.
+    pub fn merge(&mut self, mut other: Self) {
+        let (mut a, mut b) = (self.list.front, other.list.front);
+        let (a_back, b_back) = (self.list.back, other.list.back);
+        self.list.len += other.list.len;
+        // `other` doesn't own its nodes anymore
+        other.list.front = None;
+        other.list.back = None;
+        other.list.len = 0;
+
+        let mut front: Link<T> = None;
+        let mut back: Link<T> = None;
+        unsafe {
+            let mut append = |node: NonNull<Node<T>>| {
+                (*node.as_ptr()).front = back;
+                match back {
+                    Some(back) => (*back.as_ptr()).back = Some(node),
+                    None => front = Some(node),
+                }
+                back = Some(node);
+            };
+            while let (Some(x), Some(y)) = (a, b) {
+                if self.cmp.compare(Self::elem(y), Self::elem(x)) == Ordering::Less {
+                    b = (*y.as_ptr()).back;
+                    append(y);
+                } else {
+                    a = (*x.as_ptr()).back;
+                    append(x);
+                }
+            }
+            // Whatever is left is already linked up
+            let (rest, rest_back) = if a.is_some() {
+                (a, a_back)
+            } else {
+                (b, b_back)
+            };
+            if let Some(rest) = rest {
+                append(rest);
+                back = rest_back;
+            }
+        }
+        self.list.front = front;
+        self.list.back = back;
+    }
+
+    // The elements within `range`, in order.
+    pub fn range<R: RangeBounds<T>>(&self, range: R) -> Range<'_, T> {
+        let before_start = |elem: &T| match range.start_bound() {
+            Bound::Included(start) => self.cmp.compare(elem, start) == Ordering::Less,
+            Bound::Excluded(start) => self.cmp.compare(elem, start) != Ordering::Greater,
+            Bound::Unbounded => false,
+        };
+        let after_end = |elem: &T| match range.end_bound() {
+            Bound::Included(end) => self.cmp.compare(elem, end) == Ordering::Greater,
+            Bound::Excluded(end) => self.cmp.compare(elem, end) != Ordering::Less,
+            Bound::Unbounded => false,
+        };
+        unsafe {
+            let mut front = self.list.front;
+            while let Some(node) = front.filter(|&node| before_start(Self::elem(node))) {
+                front = (*node.as_ptr()).back;
+            }
+            let mut back = self.list.back;
+            while let Some(node) = back.filter(|&node| after_end(Self::elem(node))) {
+                back = (*node.as_ptr()).front;
+            }
+            // An empty range in the middle leaves the ends crossed over
+            if let (Some(f), Some(b)) = (front, back) {
+                if self.cmp.compare(Self::elem(f), Self::elem(b)) == Ordering::Greater {
+                    front = None;
+                }
+            }
+            if front.is_none() || back.is_none() {
+                front = None;
+                back = None;
+            }
+            Range {
+                front,
+                back,
+                _boo: PhantomData,
+            }
+        }
+    }
+
+    // The node a new `elem` goes after: the last one not greater than it.
+    // Looks from both ends, one step each in turn. Everything the front scan
+    // passes is <= `elem` and everything the back scan passes is > `elem`, so
+    // one of them stops before they cross.
+    fn insert_point(&self, elem: &T) -> Link<T> {
+        let (mut front, mut back) = (self.list.front, self.list.back);
+        unsafe {
+            while let (Some(f), Some(b)) = (front, back) {
+                if self.cmp.compare(Self::elem(f), elem) == Ordering::Greater {
+                    return (*f.as_ptr()).front;
+                }
+                if self.cmp.compare(Self::elem(b), elem) != Ordering::Greater {
+                    return Some(b);
+                }
+                front = (*f.as_ptr()).back;
+                back = (*b.as_ptr()).front;
+            }
+        }
+        // Only for the empty list
+        None
+    }
+
+    // The first node equal to `elem`, looking from both ends like `insert_point`.
+    fn find(&self, elem: &T) -> Link<T> {
+        let (mut front, mut back) = (self.list.front, self.list.back);
+        unsafe {
+            while let (Some(f), Some(b)) = (front, back) {
+                match self.cmp.compare(Self::elem(f), elem) {
+                    Ordering::Less => {}
+                    Ordering::Equal => return Some(f),
+                    Ordering::Greater => return None,
+                }
+                match self.cmp.compare(Self::elem(b), elem) {
+                    Ordering::Greater => {}
+                    Ordering::Less => return None,
+                    Ordering::Equal => {
+                        // Back up to the first of the equal ones
+                        let mut first = b;
+                        while let Some(prev) = (*first.as_ptr()).front {
+                            if self.cmp.compare(Self::elem(prev), elem) != Ordering::Equal {
+                                break;
+                            }
+                            first = prev;
+                        }
+                        return Some(first);
+                    }
+                }
+                front = (*f.as_ptr()).back;
+                back = (*b.as_ptr()).front;
+            }
+        }
+        None
+    }
+}
+
+impl<T: Ord> Default for SortedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone, C: Clone> Clone for SortedList<T, C> {
+    fn clone(&self) -> Self {
+        SortedList {
+            list: self.list.clone(),
+            cmp: self.cmp.clone(),
+        }
+    }
+}
+
+impl<T, C: Compare<T>> Extend<T> for SortedList<T, C> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.insert(elem);
+        }
+    }
+}
+
+impl<T: Ord> FromIterator<T> for SortedList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T: Ord> From<LinkedList<T>> for SortedList<T> {
+    fn from(list: LinkedList<T>) -> Self {
+        list.into_iter().collect()
+    }
+}
+
+impl<T, C> From<SortedList<T, C>> for LinkedList<T> {
+    fn from(list: SortedList<T, C>) -> Self {
+        list.into_list()
+    }
+}
+
+impl<T: fmt::Debug, C> fmt::Debug for SortedList<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.list.fmt(f)
+    }
+}
+
+impl<T: PartialEq, C> PartialEq for SortedList<T, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.list == other.list
+    }
+}
+
+impl<T: Eq, C> Eq for SortedList<T, C> {}
+
+impl<T, C> IntoIterator for SortedList<T, C> {
+    type IntoIter = super::iterator::IntoIter<T>;
+    type Item = T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.list.into_iter()
+    }
+}
+
+impl<'a, T, C> IntoIterator for &'a SortedList<T, C> {
+    type IntoIter = super::iterator::Iter<'a, T>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// Ranges don't know their length up front, they stop when the ends meet.
+pub struct Range<'a, T> {
+    front: Link<T>,
+    back: Link<T>,
+    _boo: PhantomData<&'a T>,
+}
+
+impl<'a, T> Iterator for Range<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.front?;
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.front = unsafe { (*node.as_ptr()).back };
+        }
+        unsafe { Some(&(*node.as_ptr()).elem) }
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Range<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let node = self.back?;
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.back = unsafe { (*node.as_ptr()).front };
+        }
+        unsafe { Some(&(*node.as_ptr()).elem) }
+    }
+}
+
+unsafe impl<'a, T: Sync> Send for Range<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for Range<'a, T> {}
+
+#[cfg(test)]
+mod test {
+    use super::SortedList;
+    use crate::sixth::LinkedList;
+    use crate::test_util::Lcg;
+    use std::cell::Cell;
+    use std::ops::Bound;
+
+    fn contents<T: Clone, C>(list: &SortedList<T, C>) -> Vec<T> {
+        list.iter().cloned().collect()
+    }
+
+    #[test]
+    fn basics() {
+        let mut list = SortedList::new();
+        assert_eq!(list.first(), None);
+        assert_eq!(list.last(), None);
+
+        for x in [5, 1, 4, 1, 3, 9, 2, 6] {
+            list.insert(x);
+        }
+        assert_eq!(contents(&list), &[1, 1, 2, 3, 4, 5, 6, 9]);
+        assert_eq!(list.len(), 8);
+        assert_eq!(list.first(), Some(&1));
+        assert_eq!(list.last(), Some(&9));
+        assert!(list.contains(&4));
+        assert!(!list.contains(&7));
+        assert!(!list.contains(&0));
+        assert!(!list.contains(&10));
+
+        assert_eq!(list.remove(&1), Some(1));
+        assert_eq!(list.remove(&7), None);
+        assert_eq!(list.remove(&9), Some(9));
+        assert_eq!(contents(&list), &[1, 2, 3, 4, 5, 6]);
+        assert_eq!(list.pop_first(), Some(1));
+        assert_eq!(list.pop_last(), Some(6));
+        assert_eq!(format!("{:?}", list), "[2, 3, 4, 5]");
+
+        let plain: LinkedList<_> = list.into();
+        assert_eq!(plain, (2..6).collect());
+        let list: SortedList<_> = LinkedList::from_iter([3, 1, 2]).into();
+        assert_eq!(contents(&list), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn stable() {
+        // Ordered by the first field only; the second records insertion order
+        let mut list = SortedList::with_comparator(|a: &(i32, i32), b: &(i32, i32)| a.0.cmp(&b.0));
+        for (i, key) in [2, 1, 2, 3, 1, 2, 3, 2].into_iter().enumerate() {
+            list.insert((key, i as i32));
+        }
+        assert_eq!(
+            contents(&list),
+            &[
+                (1, 1),
+                (1, 4),
+                (2, 0),
+                (2, 2),
+                (2, 5),
+                (2, 7),
+                (3, 3),
+                (3, 6)
+            ]
+        );
+
+        // `remove` and `contains` go by the comparator too, and take the first
+        assert!(list.contains(&(2, 99)));
+        assert_eq!(list.remove(&(2, 99)), Some((2, 0)));
+        assert_eq!(list.remove(&(3, 99)), Some((3, 3)));
+        assert_eq!(list.remove(&(1, 99)), Some((1, 1)));
+        assert_eq!(contents(&list), &[(1, 4), (2, 2), (2, 5), (2, 7), (3, 6)]);
+    }
+
+    #[test]
+    fn reverse_comparator() {
+        let mut list = SortedList::with_comparator(|a: &i32, b: &i32| b.cmp(a));
+        list.extend([3, 7, 1, 5]);
+        assert_eq!(contents(&list), &[7, 5, 3, 1]);
+        assert_eq!(
+            list.range((Bound::Included(6), Bound::Included(2)))
+                .copied()
+                .collect::<Vec<_>>(),
+            &[5, 3]
+        );
+    }
+
+    #[test]
+    fn searches_from_the_nearer_end() {
+        let comparisons = Cell::new(0);
+        let mut list = SortedList::with_comparator(|a: &i32, b: &i32| {
+            comparisons.set(comparisons.get() + 1);
+            a.cmp(b)
+        });
+        for x in 0..1000 {
+            list.insert(x * 10);
+        }
+        // Growing at the back: one look at each end per insert
+        assert!(comparisons.get() <= 2 * 1000);
+
+        for (x, most) in [(-5, 1), (9995, 2), (25, 12), (9975, 12)] {
+            comparisons.set(0);
+            list.insert(x);
+            assert!(
+                comparisons.get() <= most,
+                "{} took {}",
+                x,
+                comparisons.get()
+            );
+        }
+        assert!(list.iter().zip(list.iter().skip(1)).all(|(a, b)| a <= b));
+    }
+
+    #[test]
+    fn merge() {
+        let mut a: SortedList<_> = [1, 3, 5, 7, 9].into_iter().collect();
+        let b: SortedList<_> = [0, 3, 4, 10, 11].into_iter().collect();
+        a.merge(b);
+        assert_eq!(contents(&a), &[0, 1, 3, 3, 4, 5, 7, 9, 10, 11]);
+        assert_eq!(a.len(), 10);
+        assert_eq!(
+            a.iter().rev().copied().collect::<Vec<_>>(),
+            &[11, 10, 9, 7, 5, 4, 3, 3, 1, 0]
+        );
+
+        a.merge(SortedList::new());
+        assert_eq!(a.len(), 10);
+        let mut empty = SortedList::new();
+        empty.merge(a.clone());
+        assert_eq!(empty, a);
+
+        // Ties keep `self`'s elements first
+        let by_key = |x: &(i32, char), y: &(i32, char)| x.0.cmp(&y.0);
+        let mut a = SortedList::with_comparator(by_key);
+        let mut b = SortedList::with_comparator(by_key);
+        a.extend([(1, 'a'), (2, 'a')]);
+        b.extend([(1, 'b'), (2, 'b'), (2, 'c')]);
+        a.merge(b);
+        assert_eq!(
+            contents(&a),
+            &[(1, 'a'), (1, 'b'), (2, 'a'), (2, 'b'), (2, 'c')]
+        );
+        assert_eq!(a.last(), Some(&(2, 'c')));
+    }
+
+    #[test]
+    fn range() {
+        let list: SortedList<_> = [1, 2, 2, 4, 6, 8].into_iter().collect();
+        let range = |r| {
+            list.range::<(Bound<i32>, Bound<i32>)>(r)
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        use Bound::*;
+
+        assert_eq!(range((Included(2), Included(6))), &[2, 2, 4, 6]);
+        assert_eq!(range((Excluded(2), Excluded(8))), &[4, 6]);
+        assert_eq!(range((Unbounded, Excluded(2))), &[1]);
+        assert_eq!(range((Included(5), Unbounded)), &[6, 8]);
+        assert_eq!(range((Included(3), Excluded(4))), &[] as &[i32]);
+        assert_eq!(range((Included(9), Unbounded)), &[] as &[i32]);
+        assert_eq!(range((Unbounded, Excluded(1))), &[] as &[i32]);
+        assert_eq!(range((Unbounded, Unbounded)).len(), 6);
+        assert_eq!(list.range(2..=2).count(), 2);
+        assert_eq!(
+            list.range(2..7).rev().copied().collect::<Vec<_>>(),
+            &[6, 4, 2, 2]
+        );
+    }
+
+    #[test]
+    fn model() {
+        let mut rng = Lcg(11);
+        let mut list = SortedList::new();
+        let mut model = Vec::new();
+        for _ in 0..3000 {
+            let x = rng.next(200);
+            match rng.next(4) {
+                0 | 1 => {
+                    list.insert(x);
+                    let at = model.partition_point(|&y| y <= x);
+                    model.insert(at, x);
+                }
+                2 => {
+                    let at = model.binary_search(&x).ok().map(|_| {
+                        let first = model.partition_point(|&y| y < x);
+                        model.remove(first)
+                    });
+                    assert_eq!(list.remove(&x), at);
+                }
+                _ => {
+                    let y = x + rng.next(30);
+                    let lo = model.partition_point(|&z| z < x);
+                    let hi = model.partition_point(|&z| z < y);
+                    assert!(list.range(x..y).eq(&model[lo..hi]));
+                }
+            }
+        }
+        assert_eq!(contents(&list), model);
+    }
+}
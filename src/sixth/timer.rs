@@ -0,0 +1,391 @@
+// A hierarchical timing wheel: O(1) to schedule and to cancel a timer, and
+// firing costs O(1) per timer plus a little per level.
+//
+// Time is a plain `u64` tick count that only moves when you call `advance`,
+// so the caller picks the unit and the clock (tests just pass numbers in).
+//
+// There are `LEVELS` wheels of 64 slots each. A level-0 slot is one tick, a
+// level-1 slot is 64 ticks, a level-2 slot 64 * 64 ticks, and so on. A timer
+// goes in the lowest level whose slot size still tells its deadline apart from
+// `now`. When time reaches the start of a higher-level slot, its timers get
+// "cascaded" into the levels below, where they are sorted more finely, until
+// they end up in level 0 and fire. Deadlines past the top level wait in an
+// `overflow` list and get placed again whenever the top level wraps.
+//
+// Every slot is a `LinkedList`, and timers move between slots (and into the
+// list of fired timers) by relinking their node, so a timer's node stays put
+// from `schedule` to firing. Handles map to those nodes, which is what makes
+// `cancel` O(1).
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+use std::ptr::NonNull;
+
+use super::{LinkedList, Node};
+
+const SLOT_BITS: usize = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+const LEVELS: usize = 6;
+// How many ticks the top level covers
+const TOP_RANGE: u64 = 1 << (SLOT_BITS * LEVELS);
+
+struct Entry<T> {
+    id: u64,
+    deadline: u64,
+    // Where the node is: `level == LEVELS` means `overflow`
+    level: usize,
+    slot: usize,
+    value: T,
+}
+
+struct Level<T> {
+    slots: Vec<LinkedList<Entry<T>>>,
+    // Bit `i` is set when `slots[i]` isn't empty
+    occupied: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerHandle(u64);
+
+pub struct TimerWheel<T> {
+    levels: Vec<Level<T>>,
+    overflow: LinkedList<Entry<T>>,
+    timers: HashMap<u64, NonNull<Node<Entry<T>>>>,
+    now: u64,
+    next_id: u64,
+}
+
+// The level a timer for `deadline` goes in: the one where `now` and
+// `deadline` first fall into different slots.
+fn level_for(now: u64, deadline: u64) -> usize {
+    let masked = (now ^ deadline) | (SLOTS as u64 - 1);
+    let significant = 63 - masked.leading_zeros() as usize;
+    significant / SLOT_BITS
+}
+
+fn slot_for(time: u64, level: usize) -> usize {
+    (time >> (level * SLOT_BITS)) as usize & (SLOTS - 1)
+}
+
+impl<T> TimerWheel<T> {
+    pub fn new() -> Self {
+        Self::starting_at(0)
+    }
+
+    pub fn starting_at(now: u64) -> Self {
+        TimerWheel {
+            levels: (0..LEVELS)
+                .map(|_| Level {
+                    slots: (0..SLOTS).map(|_| LinkedList::new()).collect(),
+                    occupied: 0,
+                })
+                .collect(),
+            overflow: LinkedList::new(),
+            timers: HashMap::new(),
+            now,
+            next_id: 0,
+        }
+    }
+
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    // How many timers haven't fired or been cancelled yet.
+    pub fn len(&self) -> usize {
+        self.timers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+
+    // A deadline that has already passed fires on the next `advance`.
+    pub fn schedule(&mut self, deadline: u64, value: T) -> TimerHandle {
+        let id = self.next_id;
+        self.next_id += 1;
+        let node = Node::alloc(Entry {
+            id,
+            deadline,
+            level: 0,
+            slot: 0,
+            value,
+        });
+        unsafe { self.place(node) };
+        self.timers.insert(id, node);
+        TimerHandle(id)
+    }
+
+    // `None` if the timer already fired or was cancelled.
+    pub fn cancel(&mut self, handle: TimerHandle) -> Option<T> {
+        let node = self.timers.remove(&handle.0)?;
+        unsafe {
+            let (level, slot) = ((*node.as_ptr()).elem.level, (*node.as_ptr()).elem.slot);
+            let entry = self.list_mut(level, slot).unlink(node);
+            if level < LEVELS && self.levels[level].slots[slot].is_empty() {
+                self.levels[level].occupied &= !(1 << slot);
+            }
+            Some(entry.value)
+        }
+    }
+
+    pub fn deadline(&self, handle: TimerHandle) -> Option<u64> {
+        let node = self.timers.get(&handle.0)?;
+        unsafe { Some((*node.as_ptr()).elem.deadline) }
+    }
+
+    pub fn is_scheduled(&self, handle: TimerHandle) -> bool {
+        self.timers.contains_key(&handle.0)
+    }
+
+    // Moves the clock to `now` and returns every timer with a deadline at or
+    // before it, earliest deadline first and in scheduling order among equal
+    // deadlines. The clock never goes backwards: an earlier `now` just fires
+    // whatever is overdue.
+    pub fn advance(&mut self, now: u64) -> impl Iterator<Item = T> {
+        let target = now.max(self.now);
+        let mut expired = LinkedList::new();
+        while let Some((level, slot, start)) = self.next_expiration() {
+            if start > target {
+                break;
+            }
+            self.now = start;
+            let mut list = mem::take(self.list_mut(level, slot));
+            if level < LEVELS {
+                self.levels[level].occupied &= !(1 << slot);
+            }
+            while let Some(node) = list.front {
+                unsafe {
+                    list.detach(node);
+                    let entry = &(*node.as_ptr()).elem;
+                    if entry.deadline <= self.now {
+                        self.timers.remove(&entry.id);
+                        expired.link_after(expired.back, node);
+                    } else {
+                        // Cascade down to a finer level
+                        self.place(node);
+                    }
+                }
+            }
+        }
+        self.now = target;
+        expired.into_iter().map(|entry| entry.value)
+    }
+
+    // The earliest slot that needs looking at, with the time it starts.
+    // Lower levels always come first: a level's timers are all in the current
+    // slot of the level above.
+    fn next_expiration(&self) -> Option<(usize, usize, u64)> {
+        for (level, wheel) in self.levels.iter().enumerate() {
+            if wheel.occupied == 0 {
+                continue;
+            }
+            let slot_range = 1u64 << (level * SLOT_BITS);
+            let level_range = slot_range << SLOT_BITS;
+            let now_slot = slot_for(self.now, level);
+            // The first occupied slot from `now`'s on. Nothing sits behind it.
+            let slot = (wheel
+                .occupied
+                .rotate_right(now_slot as u32)
+                .trailing_zeros() as usize
+                + now_slot)
+                % SLOTS;
+            let start = (self.now & !(level_range - 1)) + slot as u64 * slot_range;
+            // Overdue timers sit in `now`'s own level-0 slot
+            return Some((level, slot, start.max(self.now)));
+        }
+        if self.overflow.is_empty() {
+            return None;
+        }
+        // The start of the next turn of the top level
+        let wrap = (self.now | (TOP_RANGE - 1)).checked_add(1)?;
+        Some((LEVELS, 0, wrap))
+    }
+
+    fn list_mut(&mut self, level: usize, slot: usize) -> &mut LinkedList<Entry<T>> {
+        if level == LEVELS {
+            &mut self.overflow
+        } else {
+            &mut self.levels[level].slots[slot]
+        }
+    }
+
+    // SAFETY: `node` must not be in any list.
+    unsafe fn place(&mut self, node: NonNull<Node<Entry<T>>>) {
+        let entry = &mut (*node.as_ptr()).elem;
+        let at = entry.deadline.max(self.now);
+        let level = level_for(self.now, at).min(LEVELS);
+        let slot = if level < LEVELS {
+            slot_for(at, level)
+        } else {
+            0
+        };
+        entry.level = level;
+        entry.slot = slot;
+        if level < LEVELS {
+            self.levels[level].occupied |= 1 << slot;
+        }
+        let list = self.list_mut(level, slot);
+        list.link_after(list.back, node);
+    }
+}
+
+impl<T> Default for TimerWheel<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for TimerWheel<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimerWheel")
+            .field("now", &self.now)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+// The node pointers in `timers` all point into lists the wheel owns.
+unsafe impl<T: Send> Send for TimerWheel<T> {}
+unsafe impl<T: Sync> Sync for TimerWheel<T> {}
+
+#[cfg(test)]
+mod test {
+    use super::{TimerWheel, TOP_RANGE};
+    use crate::test_util::Lcg;
+    use std::collections::BTreeMap;
+
+    fn fired<T>(wheel: &mut TimerWheel<T>, now: u64) -> Vec<T> {
+        wheel.advance(now).collect()
+    }
+
+    #[test]
+    fn basics() {
+        let mut wheel = TimerWheel::new();
+        assert!(fired(&mut wheel, 10).is_empty());
+        assert_eq!(wheel.now(), 10);
+
+        wheel.schedule(15, "b");
+        wheel.schedule(13, "a");
+        wheel.schedule(20, "c");
+        wheel.schedule(15, "b2");
+        assert_eq!(wheel.len(), 4);
+
+        assert!(fired(&mut wheel, 12).is_empty());
+        assert_eq!(fired(&mut wheel, 13), &["a"]);
+        assert_eq!(fired(&mut wheel, 19), &["b", "b2"]);
+        assert_eq!(fired(&mut wheel, 100), &["c"]);
+        assert!(wheel.is_empty());
+    }
+
+    #[test]
+    fn overdue_and_backwards() {
+        let mut wheel = TimerWheel::starting_at(1000);
+        wheel.schedule(5, 'a');
+        wheel.schedule(1000, 'b');
+        wheel.schedule(1001, 'c');
+        // Going "back" doesn't move the clock, but overdue timers still fire
+        assert_eq!(fired(&mut wheel, 0), &['a', 'b']);
+        assert_eq!(wheel.now(), 1000);
+        assert_eq!(fired(&mut wheel, 1001), &['c']);
+    }
+
+    #[test]
+    fn cancel() {
+        let mut wheel = TimerWheel::new();
+        let a = wheel.schedule(5, 'a');
+        let b = wheel.schedule(5, 'b');
+        let far = wheel.schedule(100_000, 'f');
+        let huge = wheel.schedule(TOP_RANGE * 3, 'h');
+        assert_eq!(wheel.deadline(far), Some(100_000));
+
+        assert_eq!(wheel.cancel(a), Some('a'));
+        assert_eq!(wheel.cancel(a), None);
+        assert!(!wheel.is_scheduled(a));
+        assert_eq!(wheel.cancel(huge), Some('h'));
+        assert_eq!(fired(&mut wheel, 10), &['b']);
+        assert_eq!(wheel.cancel(b), None);
+
+        // Cancelling after it has been cascaded down a few levels
+        assert!(fired(&mut wheel, 99_999).is_empty());
+        assert_eq!(wheel.cancel(far), Some('f'));
+        assert!(fired(&mut wheel, TOP_RANGE * 4).is_empty());
+        assert!(wheel.is_empty());
+    }
+
+    #[test]
+    fn every_level() {
+        let mut wheel = TimerWheel::new();
+        let deadlines = [
+            1,
+            63,
+            64,
+            65,
+            4095,
+            4096,
+            300_000,
+            20_000_000,
+            1 << 30,
+            TOP_RANGE - 1,
+            TOP_RANGE,
+            TOP_RANGE * 5 + 17,
+        ];
+        for &d in deadlines.iter().rev() {
+            wheel.schedule(d, d);
+        }
+        // Stepping one tick before and then onto each deadline
+        for &d in &deadlines {
+            assert!(fired(&mut wheel, d - 1).is_empty(), "{} fired early", d);
+            assert_eq!(fired(&mut wheel, d), &[d]);
+        }
+        assert!(wheel.is_empty());
+    }
+
+    #[test]
+    fn model() {
+        let mut rng = Lcg(13);
+        let mut wheel = TimerWheel::new();
+        // (deadline, schedule order) -> handle
+        let mut model = BTreeMap::new();
+        let mut handles = Vec::new();
+        let mut now = 0;
+        for i in 0..5_000u64 {
+            match rng.next(10) {
+                0..=4 => {
+                    // Mostly short timeouts, some very long ones
+                    let delay = match rng.next(4) {
+                        0 => rng.next(1 << 20),
+                        _ => rng.next(200),
+                    };
+                    let handle = wheel.schedule(now + delay, (now + delay, i));
+                    model.insert((now + delay, i), handle);
+                    handles.push(handle);
+                }
+                5 => {
+                    if !handles.is_empty() {
+                        let handle = handles.swap_remove(rng.next(handles.len()));
+                        let cancelled = wheel.cancel(handle);
+                        if let Some(key) = cancelled {
+                            assert_eq!(model.remove(&key), Some(handle));
+                        }
+                        assert!(!model.values().any(|&h| h == handle));
+                    }
+                }
+                _ => {
+                    now += match rng.next(8) {
+                        0 => rng.next(1 << 16),
+                        _ => rng.next(50),
+                    };
+                    let expected: Vec<_> = model.range(..(now + 1, 0)).map(|(&k, _)| k).collect();
+                    for key in &expected {
+                        model.remove(key);
+                    }
+                    assert_eq!(fired(&mut wheel, now), expected);
+                }
+            }
+            assert_eq!(wheel.len(), model.len());
+        }
+        let expected: Vec<_> = model.keys().copied().collect();
+        assert_eq!(fired(&mut wheel, u64::MAX / 2), expected);
+    }
+}
@@ -0,0 +1,1062 @@
+// A skip list: a sorted singly linked list, plus extra "express lane" links so
+// searches can skip ahead. Every node is on level 0; each one is also on the
+// levels above it with probability 1/2 per level, so a search only looks at
+// O(log n) nodes on average.
+//
+// Level 0 also has `prev` links (like `sixth`'s `front`), which is what makes
+// `last`, `iter().rev()` and double-ended ranges cheap.
+//
+// Node heights come from a small xorshift generator. `new` seeds it randomly,
+// `with_seed` makes the whole shape of the list reproducible, which is what
+// the tests (and anyone debugging a `{:?}` dump) want.
+//
+// Inserts only touch the handful of links around the new node, which is what
+// makes skip lists easy to make lock-free later. This one is still `&mut self`
+// everywhere; wrap it in a lock to share it for now.
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+use std::ptr::NonNull;
+
+const MAX_LEVEL: usize = 16;
+
+type Link<K, V> = Option<NonNull<Node<K, V>>>;
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    prev: Link<K, V>,
+    // One link per level this node is on
+    next: Box<[Link<K, V>]>,
+}
+
+impl<K, V> Node<K, V> {
+    unsafe fn height(node: NonNull<Self>) -> usize {
+        (*node.as_ptr()).next.as_ref().len()
+    }
+}
+
+// xorshift64*, only used to pick node heights.
+#[derive(Clone)]
+struct LevelGen(u64);
+
+impl LevelGen {
+    fn new(seed: u64) -> Self {
+        // xorshift gets stuck on zero
+        LevelGen(if seed == 0 {
+            0x9E37_79B9_7F4A_7C15
+        } else {
+            seed
+        })
+    }
+
+    fn next_height(&mut self) -> usize {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        // Each extra level is a coin flip
+        let bits = x.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        (bits.trailing_ones() as usize + 1).min(MAX_LEVEL)
+    }
+}
+
+pub struct SkipList<K, V> {
+    head: [Link<K, V>; MAX_LEVEL],
+    tail: Link<K, V>,
+    // How many levels are in use
+    level: usize,
+    len: usize,
+    levels: LevelGen,
+    _boo: PhantomData<(K, V)>,
+}
+
+impl<K, V> SkipList<K, V> {
+    pub fn new() -> Self {
+        let seed = RandomState::new().build_hasher().finish();
+        Self::with_seed(seed)
+    }
+
+    // The same seed and the same sequence of inserts give the same list,
+    // down to which node is on which level.
+    pub fn with_seed(seed: u64) -> Self {
+        SkipList {
+            head: [None; MAX_LEVEL],
+            tail: None,
+            level: 0,
+            len: 0,
+            levels: LevelGen::new(seed),
+            _boo: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn first(&self) -> Option<(&K, &V)> {
+        unsafe { self.head[0].map(|node| Self::entry(node)) }
+    }
+
+    pub fn last(&self) -> Option<(&K, &V)> {
+        unsafe { self.tail.map(|node| Self::entry(node)) }
+    }
+
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        let node = self.head[0]?;
+        unsafe {
+            let height = Node::height(node);
+            for level in 0..height {
+                self.head[level] = (*node.as_ptr()).next[level];
+            }
+            Some(self.unlink(node))
+        }
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop_first().is_some() {}
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            front: self.head[0],
+            back: self.tail,
+            len: self.len,
+            _boo: PhantomData,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            front: self.head[0],
+            back: self.tail,
+            len: self.len,
+            _boo: PhantomData,
+        }
+    }
+
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> + ExactSizeIterator {
+        self.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> + ExactSizeIterator {
+        self.iter().map(|(_, v)| v)
+    }
+
+    unsafe fn entry<'a>(node: NonNull<Node<K, V>>) -> (&'a K, &'a V) {
+        let node = &*node.as_ptr();
+        (&node.key, &node.value)
+    }
+
+    // The link out of `node` on `level`, where `None` stands for the head.
+    fn next_at(&self, node: Link<K, V>, level: usize) -> Link<K, V> {
+        match node {
+            Some(node) => unsafe { (*node.as_ptr()).next[level] },
+            None => self.head[level],
+        }
+    }
+
+    fn slot_at(&mut self, node: Link<K, V>, level: usize) -> &mut Link<K, V> {
+        match node {
+            Some(node) => unsafe { &mut (*node.as_ptr()).next[level] },
+            None => &mut self.head[level],
+        }
+    }
+
+    // For every level in use, the last node whose key satisfies `before`
+    // (`None` for the head). `before` has to be true for a prefix of the keys
+    // and false after that, like `< key` or `<= key`.
+    fn preds(&self, before: impl Fn(&K) -> bool) -> [Link<K, V>; MAX_LEVEL] {
+        let mut preds = [None; MAX_LEVEL];
+        let mut cur = None;
+        for level in (0..self.level).rev() {
+            while let Some(next) = self.next_at(cur, level) {
+                if !before(unsafe { &(*next.as_ptr()).key }) {
+                    break;
+                }
+                cur = Some(next);
+            }
+            preds[level] = cur;
+        }
+        preds
+    }
+
+    // The last node whose key satisfies `before`, see `preds`.
+    fn last_where(&self, before: impl Fn(&K) -> bool) -> Link<K, V> {
+        if self.level == 0 {
+            return None;
+        }
+        self.preds(before)[0]
+    }
+
+    // Frees `node` and fixes up the `prev` links and `level`. The caller has
+    // already bypassed it on every level.
+    unsafe fn unlink(&mut self, node: NonNull<Node<K, V>>) -> (K, V) {
+        let node = Box::from_raw(node.as_ptr());
+        match node.next[0] {
+            Some(next) => (*next.as_ptr()).prev = node.prev,
+            None => self.tail = node.prev,
+        }
+        while self.level > 0 && self.head[self.level - 1].is_none() {
+            self.level -= 1;
+        }
+        self.len -= 1;
+        (node.key, node.value)
+    }
+}
+
+impl<K: Ord, V> SkipList<K, V> {
+    // Returns the old value if `key` was already there. The key itself isn't
+    // replaced, like `BTreeMap::insert`.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let mut preds = self.preds(|k| *k < key);
+        if let Some(next) = self.next_at(preds[0], 0) {
+            let next = unsafe { &mut *next.as_ptr() };
+            if next.key == key {
+                return Some(std::mem::replace(&mut next.value, value));
+            }
+        }
+
+        let height = self.levels.next_height();
+        // New levels start out empty, so their predecessor is the head
+        for pred in preds.iter_mut().take(height).skip(self.level) {
+            *pred = None;
+        }
+        self.level = self.level.max(height);
+
+        let new = Box::new(Node {
+            key,
+            value,
+            prev: preds[0],
+            next: vec![None; height].into_boxed_slice(),
+        });
+        let new = unsafe { NonNull::new_unchecked(Box::into_raw(new)) };
+        for (level, &pred) in preds.iter().enumerate().take(height) {
+            let slot = self.slot_at(pred, level);
+            let next = slot.replace(new);
+            unsafe { (*new.as_ptr()).next[level] = next };
+        }
+        match unsafe { (*new.as_ptr()).next[0] } {
+            Some(next) => unsafe { (*next.as_ptr()).prev = Some(new) },
+            None => self.tail = Some(new),
+        }
+        self.len += 1;
+        None
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let preds = self.preds(|k| k.borrow() < key);
+        let node = self.next_at(preds[0], 0)?;
+        unsafe {
+            if (*node.as_ptr()).key.borrow() != key {
+                return None;
+            }
+            let height = Node::height(node);
+            for (level, &pred) in preds.iter().enumerate().take(height) {
+                let next = (*node.as_ptr()).next[level];
+                *self.slot_at(pred, level) = next;
+            }
+            Some(self.unlink(node))
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find(key)
+            .map(|node| unsafe { &(*node.as_ptr()).value })
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find(key)
+            .map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find(key).is_some()
+    }
+
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        let node = self.tail?;
+        let key = unsafe { &(*node.as_ptr()).key };
+        let preds = self.preds(|k| k < key);
+        unsafe {
+            let height = Node::height(node);
+            for (level, &pred) in preds.iter().enumerate().take(height) {
+                *self.slot_at(pred, level) = None;
+            }
+            Some(self.unlink(node))
+        }
+    }
+
+    // The entries with keys in `range`, in order. Both ends are found by a
+    // search, so this is O(log n) plus the length of the range.
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let front = match range.start_bound() {
+            Bound::Included(start) => self.next_at(self.last_where(|k| k.borrow() < start), 0),
+            Bound::Excluded(start) => self.next_at(self.last_where(|k| k.borrow() <= start), 0),
+            Bound::Unbounded => self.head[0],
+        };
+        let back = match range.end_bound() {
+            Bound::Included(end) => self.last_where(|k| k.borrow() <= end),
+            Bound::Excluded(end) => self.last_where(|k| k.borrow() < end),
+            Bound::Unbounded => self.tail,
+        };
+        // An empty range can leave the ends crossed over
+        let crossed = match (front, back) {
+            (Some(front), Some(back)) => unsafe { (*front.as_ptr()).key > (*back.as_ptr()).key },
+            _ => true,
+        };
+        if crossed {
+            Range {
+                front: None,
+                back: None,
+                _boo: PhantomData,
+            }
+        } else {
+            Range {
+                front,
+                back,
+                _boo: PhantomData,
+            }
+        }
+    }
+
+    fn find<Q>(&self, key: &Q) -> Link<K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let node = self.next_at(self.last_where(|k| k.borrow() < key), 0)?;
+        if unsafe { (*node.as_ptr()).key.borrow() } == key {
+            Some(node)
+        } else {
+            None
+        }
+    }
+}
+
+impl<K, V> Drop for SkipList<K, V> {
+    fn drop(&mut self) {
+        // Level 0 has every node, the other levels can just be forgotten
+        let mut cur = self.head[0];
+        while let Some(node) = cur {
+            let node = unsafe { Box::from_raw(node.as_ptr()) };
+            cur = node.next[0];
+        }
+    }
+}
+
+impl<K, V> Default for SkipList<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The clone copies every node's height and gets its own copy of the level
+// generator, so it has the same shape and carries on the same way.
+impl<K: Clone, V: Clone> Clone for SkipList<K, V> {
+    fn clone(&self) -> Self {
+        let mut list = SkipList::with_seed(0);
+        list.levels = self.levels.clone();
+        list.level = self.level;
+        // The last node so far on each level, `None` for the head
+        let mut lasts: [Link<K, V>; MAX_LEVEL] = [None; MAX_LEVEL];
+        let mut cur = self.head[0];
+        while let Some(node) = cur {
+            let node = unsafe { &*node.as_ptr() };
+            let new = Box::new(Node {
+                key: node.key.clone(),
+                value: node.value.clone(),
+                prev: list.tail,
+                next: vec![None; node.next.len()].into_boxed_slice(),
+            });
+            let new = unsafe { NonNull::new_unchecked(Box::into_raw(new)) };
+            for (level, last) in lasts.iter_mut().enumerate().take(node.next.len()) {
+                *list.slot_at(*last, level) = Some(new);
+                *last = Some(new);
+            }
+            list.tail = Some(new);
+            list.len += 1;
+            cur = node.next[0];
+        }
+        list
+    }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for SkipList<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for SkipList<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<K: PartialEq, V: PartialEq> PartialEq for SkipList<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other)
+    }
+}
+
+impl<K: Eq, V: Eq> Eq for SkipList<K, V> {}
+
+// Prints every level, top first, then the entries. For a seeded list:
+//   SkipList { levels: [[3], [1, 3], [1, 2, 3]], entries: {1: "a", 2: "b", 3: "c"} }
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for SkipList<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Level<'a, K, V>(&'a SkipList<K, V>, usize);
+
+        impl<'a, K: fmt::Debug, V> fmt::Debug for Level<'a, K, V> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let mut list = f.debug_list();
+                let mut cur = self.0.head[self.1];
+                while let Some(node) = cur {
+                    let node = unsafe { &*node.as_ptr() };
+                    list.entry(&node.key);
+                    cur = node.next[self.1];
+                }
+                list.finish()
+            }
+        }
+
+        struct Levels<'a, K, V>(&'a SkipList<K, V>);
+
+        impl<'a, K: fmt::Debug, V> fmt::Debug for Levels<'a, K, V> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_list()
+                    .entries((0..self.0.level).rev().map(|level| Level(self.0, level)))
+                    .finish()
+            }
+        }
+
+        struct Entries<'a, K, V>(&'a SkipList<K, V>);
+
+        impl<'a, K: fmt::Debug, V: fmt::Debug> fmt::Debug for Entries<'a, K, V> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_map().entries(self.0.iter()).finish()
+            }
+        }
+
+        f.debug_struct("SkipList")
+            .field("levels", &Levels(self))
+            .field("entries", &Entries(self))
+            .finish()
+    }
+}
+
+unsafe impl<K: Send, V: Send> Send for SkipList<K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for SkipList<K, V> {}
+
+pub struct IntoIter<K, V>(SkipList<K, V>);
+
+pub struct Iter<'a, K, V> {
+    front: Link<K, V>,
+    back: Link<K, V>,
+    len: usize,
+    _boo: PhantomData<&'a (K, V)>,
+}
+
+pub struct IterMut<'a, K, V> {
+    front: Link<K, V>,
+    back: Link<K, V>,
+    len: usize,
+    _boo: PhantomData<&'a mut (K, V)>,
+}
+
+// Ranges don't know their length up front, they stop when the ends meet.
+pub struct Range<'a, K, V> {
+    front: Link<K, V>,
+    back: Link<K, V>,
+    _boo: PhantomData<&'a (K, V)>,
+}
+
+impl<K, V> IntoIterator for SkipList<K, V> {
+    type IntoIter = IntoIter<K, V>;
+    type Item = (K, V);
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a SkipList<K, V> {
+    type IntoIter = Iter<'a, K, V>;
+    type Item = (&'a K, &'a V);
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut SkipList<K, V> {
+    type IntoIter = IterMut<'a, K, V>;
+    type Item = (&'a K, &'a mut V);
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop_first()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.front.map(|node| unsafe {
+            self.len -= 1;
+            self.front = (*node.as_ptr()).next[0];
+            SkipList::entry(node)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.back.map(|node| unsafe {
+            self.len -= 1;
+            self.back = (*node.as_ptr()).prev;
+            SkipList::entry(node)
+        })
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.front.map(|node| unsafe {
+            self.len -= 1;
+            let node = &mut *node.as_ptr();
+            self.front = node.next[0];
+            (&node.key, &mut node.value)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for IterMut<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.back.map(|node| unsafe {
+            self.len -= 1;
+            let node = &mut *node.as_ptr();
+            self.back = node.prev;
+            (&node.key, &mut node.value)
+        })
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for IterMut<'a, K, V> {}
+
+impl<'a, K, V> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.front?;
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.front = unsafe { (*node.as_ptr()).next[0] };
+        }
+        Some(unsafe { SkipList::entry(node) })
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Range<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let node = self.back?;
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.back = unsafe { (*node.as_ptr()).prev };
+        }
+        Some(unsafe { SkipList::entry(node) })
+    }
+}
+
+unsafe impl<'a, K: Sync, V: Sync> Send for Iter<'a, K, V> {}
+unsafe impl<'a, K: Sync, V: Sync> Sync for Iter<'a, K, V> {}
+unsafe impl<'a, K: Sync, V: Send> Send for IterMut<'a, K, V> {}
+unsafe impl<'a, K: Sync, V: Sync> Sync for IterMut<'a, K, V> {}
+unsafe impl<'a, K: Sync, V: Sync> Send for Range<'a, K, V> {}
+unsafe impl<'a, K: Sync, V: Sync> Sync for Range<'a, K, V> {}
+
+// A `SkipList` with no values.
+#[derive(Default, PartialEq, Eq)]
+pub struct SkipSet<K> {
+    map: SkipList<K, ()>,
+}
+
+impl<K> SkipSet<K> {
+    pub fn new() -> Self {
+        SkipSet {
+            map: SkipList::new(),
+        }
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        SkipSet {
+            map: SkipList::with_seed(seed),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn first(&self) -> Option<&K> {
+        self.map.first().map(|(k, _)| k)
+    }
+
+    pub fn last(&self) -> Option<&K> {
+        self.map.last().map(|(k, _)| k)
+    }
+
+    pub fn pop_first(&mut self) -> Option<K> {
+        self.map.pop_first().map(|(k, _)| k)
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+
+    pub fn iter(&self) -> SetIter<'_, K> {
+        SetIter(self.map.iter())
+    }
+}
+
+impl<K: Ord> SkipSet<K> {
+    // Returns whether `key` was new.
+    pub fn insert(&mut self, key: K) -> bool {
+        self.map.insert(key, ()).is_none()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.remove(key).is_some()
+    }
+
+    pub fn take<Q>(&mut self, key: &Q) -> Option<K>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.remove_entry(key).map(|(k, _)| k)
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.contains_key(key)
+    }
+
+    pub fn pop_last(&mut self) -> Option<K> {
+        self.map.pop_last().map(|(k, _)| k)
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> SetRange<'_, K>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        SetRange(self.map.range(range))
+    }
+}
+
+impl<K: Clone> Clone for SkipSet<K> {
+    fn clone(&self) -> Self {
+        SkipSet {
+            map: self.map.clone(),
+        }
+    }
+}
+
+impl<K: Ord> Extend<K> for SkipSet<K> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        self.map.extend(iter.into_iter().map(|k| (k, ())));
+    }
+}
+
+impl<K: Ord> FromIterator<K> for SkipSet<K> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+// Same as the map's, without the entries.
+impl<K: fmt::Debug> fmt::Debug for SkipSet<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Levels<'a, K>(&'a SkipList<K, ()>);
+
+        impl<'a, K: fmt::Debug> fmt::Debug for Levels<'a, K> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let mut levels = f.debug_list();
+                for level in (0..self.0.level).rev() {
+                    let mut keys = Vec::new();
+                    let mut cur = self.0.head[level];
+                    while let Some(node) = cur {
+                        let node = unsafe { &*node.as_ptr() };
+                        keys.push(&node.key);
+                        cur = node.next[level];
+                    }
+                    levels.entry(&keys);
+                }
+                levels.finish()
+            }
+        }
+
+        f.debug_struct("SkipSet")
+            .field("levels", &Levels(&self.map))
+            .finish()
+    }
+}
+
+pub struct SetIter<'a, K>(Iter<'a, K, ()>);
+
+pub struct SetRange<'a, K>(Range<'a, K, ()>);
+
+pub struct SetIntoIter<K>(IntoIter<K, ()>);
+
+impl<K> IntoIterator for SkipSet<K> {
+    type IntoIter = SetIntoIter<K>;
+    type Item = K;
+
+    fn into_iter(self) -> Self::IntoIter {
+        SetIntoIter(self.map.into_iter())
+    }
+}
+
+impl<'a, K> IntoIterator for &'a SkipSet<K> {
+    type IntoIter = SetIter<'a, K>;
+    type Item = &'a K;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K> Iterator for SetIter<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, K> DoubleEndedIterator for SetIter<'a, K> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(k, _)| k)
+    }
+}
+
+impl<'a, K> ExactSizeIterator for SetIter<'a, K> {}
+
+impl<'a, K> Iterator for SetRange<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, _)| k)
+    }
+}
+
+impl<'a, K> DoubleEndedIterator for SetRange<'a, K> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(k, _)| k)
+    }
+}
+
+impl<K> Iterator for SetIntoIter<K> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K> ExactSizeIterator for SetIntoIter<K> {}
+
+#[cfg(test)]
+mod test {
+    use super::{SkipList, SkipSet, MAX_LEVEL};
+    use crate::test_util::Lcg;
+    use std::collections::BTreeMap;
+    use std::ops::Bound;
+
+    // Every level is sorted, and is a subsequence of the one below it.
+    fn check<K: Ord + Clone, V>(list: &SkipList<K, V>) {
+        let level_keys = |level: usize| {
+            let mut keys = Vec::new();
+            let mut cur = list.head[level];
+            while let Some(node) = cur {
+                let node = unsafe { &*node.as_ptr() };
+                keys.push(node.key.clone());
+                cur = node.next[level];
+            }
+            keys
+        };
+        let bottom = level_keys(0);
+        assert_eq!(bottom.len(), list.len());
+        assert!(bottom.windows(2).all(|w| w[0] < w[1]));
+        for level in 1..MAX_LEVEL {
+            let keys = level_keys(level);
+            assert_eq!(keys.is_empty(), level >= list.level);
+            assert!(keys.iter().all(|k| bottom.binary_search(k).is_ok()));
+            assert!(keys.windows(2).all(|w| w[0] < w[1]));
+        }
+        let backwards: Vec<_> = list.iter().rev().map(|(k, _)| k.clone()).collect();
+        assert!(backwards.iter().rev().eq(bottom.iter()));
+    }
+
+    #[test]
+    fn basics() {
+        let mut list = SkipList::with_seed(1);
+        assert_eq!(list.first(), None);
+        assert_eq!(list.last(), None);
+        assert_eq!(list.get(&1), None);
+
+        assert_eq!(list.insert(3, "c"), None);
+        assert_eq!(list.insert(1, "a"), None);
+        assert_eq!(list.insert(2, "b"), None);
+        assert_eq!(list.insert(2, "B"), Some("b"));
+        assert_eq!(list.len(), 3);
+        check(&list);
+
+        assert_eq!(list.get(&2), Some(&"B"));
+        assert!(list.contains_key(&3));
+        assert!(!list.contains_key(&4));
+        *list.get_mut(&3).unwrap() = "C";
+        assert_eq!(list.first(), Some((&1, &"a")));
+        assert_eq!(list.last(), Some((&3, &"C")));
+
+        assert_eq!(list.remove(&2), Some("B"));
+        assert_eq!(list.remove(&2), None);
+        check(&list);
+        assert_eq!(list.pop_last(), Some((3, "C")));
+        assert_eq!(list.pop_first(), Some((1, "a")));
+        assert!(list.is_empty());
+        check(&list);
+    }
+
+    #[test]
+    fn borrowed_keys() {
+        let mut list = SkipList::with_seed(2);
+        list.insert(String::from("b"), 2);
+        list.insert(String::from("a"), 1);
+        assert_eq!(list.get("a"), Some(&1));
+        assert_eq!(
+            list.range::<str, _>((Bound::Included("b"), Bound::Unbounded))
+                .count(),
+            1
+        );
+        assert_eq!(list.remove("b"), Some(2));
+    }
+
+    #[test]
+    fn range() {
+        let list: SkipList<_, _> = (0..20).map(|i| (i * 2, i)).collect();
+        let keys = |r: super::Range<'_, i32, i32>| r.map(|(k, _)| *k).collect::<Vec<_>>();
+
+        assert_eq!(keys(list.range(4..10)), &[4, 6, 8]);
+        assert_eq!(keys(list.range(3..=10)), &[4, 6, 8, 10]);
+        assert_eq!(keys(list.range(..3)), &[0, 2]);
+        assert_eq!(keys(list.range(35..)), &[36, 38]);
+        assert_eq!(
+            keys(list.range((Bound::Excluded(4), Bound::Excluded(10)))),
+            &[6, 8]
+        );
+        assert_eq!(keys(list.range(5..6)), &[] as &[i32]);
+        assert_eq!(
+            keys(list.range((Bound::Included(10), Bound::Excluded(4)))),
+            &[] as &[i32]
+        );
+        assert_eq!(keys(list.range(100..)), &[] as &[i32]);
+        assert_eq!(list.range(..).count(), 20);
+
+        let mut range = list.range(10..=16);
+        assert_eq!(range.next_back().map(|(k, _)| *k), Some(16));
+        assert_eq!(range.next().map(|(k, _)| *k), Some(10));
+        assert_eq!(keys(range), &[12, 14]);
+    }
+
+    #[test]
+    fn model() {
+        let mut rng = Lcg(3);
+        let mut list = SkipList::with_seed(3);
+        let mut model = BTreeMap::new();
+        for i in 0..4_000 {
+            let key = rng.next(500);
+            match rng.next(3) {
+                0 | 1 => assert_eq!(list.insert(key, i), model.insert(key, i)),
+                _ => assert_eq!(list.remove(&key), model.remove(&key)),
+            }
+        }
+        check(&list);
+        assert!(list.iter().map(|(k, v)| (*k, *v)).eq(model.clone()));
+        assert!(list.iter().rev().eq(model.iter().rev()));
+        for _ in 0..100 {
+            let (a, b) = (rng.next(500), rng.next(500));
+            let (lo, hi) = (a.min(b), a.max(b));
+            assert!(list.range(lo..hi).eq(model.range(lo..hi)));
+            assert!(list.range(lo..=hi).rev().eq(model.range(lo..=hi).rev()));
+        }
+
+        for (_, v) in &mut list {
+            *v += 1;
+        }
+        let popped: Vec<_> = list.clone().into_iter().collect();
+        assert!(popped
+            .iter()
+            .map(|(k, v)| (k, v - 1))
+            .eq(model.iter().map(|(k, v)| (k, *v))));
+    }
+
+    #[test]
+    fn seeded_is_reproducible() {
+        let build = |seed| {
+            let mut list = SkipList::with_seed(seed);
+            for key in [5, 1, 4, 2, 3, 9, 7, 8, 6, 0] {
+                list.insert(key, key * 10);
+            }
+            format!("{:?}", list)
+        };
+        assert_eq!(build(42), build(42));
+        assert!(build(42).starts_with("SkipList { levels: [["));
+        assert!(build(42).ends_with("[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]], entries: {0: 0, 1: 10, 2: 20, 3: 30, 4: 40, 5: 50, 6: 60, 7: 70, 8: 80, 9: 90} }"));
+        // The clone keeps the shape
+        let mut list = SkipList::with_seed(42);
+        list.extend((0..10).map(|k| (k, ())));
+        assert_eq!(format!("{:?}", list.clone()), format!("{:?}", list));
+
+        let empty = SkipList::<i32, i32>::with_seed(1);
+        assert_eq!(
+            format!("{:?}", empty),
+            "SkipList { levels: [], entries: {} }"
+        );
+    }
+
+    #[test]
+    fn set() {
+        let mut set = SkipSet::with_seed(5);
+        assert!(set.insert(3));
+        assert!(set.insert(1));
+        assert!(!set.insert(3));
+        set.extend([7, 5]);
+        assert_eq!(set.len(), 4);
+        assert!(set.contains(&5));
+        assert_eq!(set.first(), Some(&1));
+        assert_eq!(set.last(), Some(&7));
+        assert_eq!(set.iter().copied().collect::<Vec<_>>(), &[1, 3, 5, 7]);
+        assert_eq!(set.iter().rev().copied().collect::<Vec<_>>(), &[7, 5, 3, 1]);
+        assert_eq!(set.range(2..6).copied().collect::<Vec<_>>(), &[3, 5]);
+
+        assert!(set.remove(&3));
+        assert!(!set.remove(&3));
+        assert_eq!(set.take(&5), Some(5));
+        assert_eq!(set.pop_last(), Some(7));
+        assert_eq!(set.pop_first(), Some(1));
+        assert!(set.is_empty());
+
+        let set: SkipSet<_> = [2, 1].into_iter().collect();
+        assert!(format!("{:?}", set).starts_with("SkipSet { levels: ["));
+        assert!(format!("{:?}", set).ends_with("[1, 2]] }"));
+        assert_eq!(set.into_iter().collect::<Vec<_>>(), &[1, 2]);
+    }
+}
@@ -1,3 +1,5 @@
+use std::borrow::Borrow;
+
 pub struct StackList<'a, T> {
     pub data: T,
     pub prev: Option<&'a StackList<'a, T>>,
@@ -17,11 +19,133 @@ impl<'a, T> StackList<'a, T> {
         callback(&mut list)
     }
 
+    // Like `push`, but the callback may mutate the frame it was given.
+    pub fn push_mut<U>(
+        prev: Option<&'a StackList<'a, T>>,
+        data: T,
+        callback: impl FnOnce(&mut StackList<'a, T>) -> U,
+    ) -> U {
+        let mut list = StackList { data, prev };
+        callback(&mut list)
+    }
+
+    // Pushes every item as its own frame, first item at the bottom, without
+    // recursing. Each frame lives in one of the `frames` slots, so the caller
+    // decides where (and how many) frames there are.
+    //
+    // Panics if `items` yields more elements than there are slots.
+    pub fn push_many<U>(
+        prev: Option<&'a StackList<'a, T>>,
+        items: impl IntoIterator<Item = T>,
+        frames: &'a mut [Option<StackList<'a, T>>],
+        callback: impl FnOnce(Option<&StackList<'a, T>>) -> U,
+    ) -> U {
+        let mut top = prev;
+        let mut slots = frames.iter_mut();
+        for data in items {
+            let slot = slots.next().expect("push_many ran out of frames");
+            top = Some(slot.insert(StackList { data, prev: top }));
+        }
+        callback(top)
+    }
+
+    // Only the top frame is ours to mutate, everything below it is shared.
+    pub fn split_top_mut(&mut self) -> (&mut T, Iter<'a, T>) {
+        (&mut self.data, Iter { next: self.prev })
+    }
+
     pub fn iter(&'a self) -> Iter<'a, T> {
         Iter { next: Some(self) }
     }
+
+    // Number of frames from this one down to the bottom.
+    pub fn depth(&self) -> usize {
+        self.iter().count()
+    }
+
+    // The element `n` frames down, where 0 is this frame.
+    pub fn nth(&self, n: usize) -> Option<&T> {
+        self.iter().nth(n)
+    }
+
+    // The innermost element matching `predicate`.
+    pub fn find(&self, mut predicate: impl FnMut(&T) -> bool) -> Option<&T> {
+        self.iter().find(|elem| predicate(elem))
+    }
+
+    pub fn contains(&self, elem: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.iter().any(|x| x == elem)
+    }
+
+    // Folds from this frame down to the bottom.
+    pub fn fold<B>(&self, init: B, f: impl FnMut(B, &T) -> B) -> B {
+        self.iter().fold(init, f)
+    }
+
+    pub fn to_vec(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<'a, K, V> StackList<'a, (K, V)> {
+    // Looks up the innermost binding for `key`, so inner frames shadow outer ones.
+    pub fn lookup<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: PartialEq + ?Sized,
+    {
+        self.iter().find(|(k, _)| k.borrow() == key).map(|(_, v)| v)
+    }
+}
+
+// A lexical scope of `(name, value)` bindings. The root scope has no frames,
+// and every `bind` pushes a new frame that only lives for its callback.
+pub struct Scope<'a, K, V> {
+    frames: Option<&'a StackList<'a, (K, V)>>,
+}
+
+impl<'a, K, V> Scope<'a, K, V> {
+    pub fn root() -> Self {
+        Scope { frames: None }
+    }
+
+    pub fn bind<U>(&self, key: K, value: V, callback: impl FnOnce(Scope<'_, K, V>) -> U) -> U {
+        StackList::push(self.frames, (key, value), |list| {
+            callback(Scope { frames: Some(list) })
+        })
+    }
+
+    pub fn lookup<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: PartialEq + ?Sized,
+    {
+        self.frames.and_then(|list| list.lookup(key))
+    }
+
+    pub fn depth(&self) -> usize {
+        self.frames.map_or(0, |list| list.depth())
+    }
+
+    pub fn frames(&self) -> Option<&'a StackList<'a, (K, V)>> {
+        self.frames
+    }
+}
+
+impl<'a, K, V> Clone for Scope<'a, K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
 }
 
+impl<'a, K, V> Copy for Scope<'a, K, V> {}
+
 impl<'a, T> Iterator for Iter<'a, T> {
     type Item = &'a T;
 
@@ -72,4 +196,96 @@ mod test {
             })
         })
     }
+
+    #[test]
+    fn inspect() {
+        StackList::push(None, 1, |list| {
+            StackList::push(Some(list), 2, |list| {
+                StackList::push(Some(list), 3, |list| {
+                    assert_eq!(list.depth(), 3);
+                    assert_eq!(list.nth(0), Some(&3));
+                    assert_eq!(list.nth(2), Some(&1));
+                    assert_eq!(list.nth(3), None);
+                    assert_eq!(list.find(|&x| x < 3), Some(&2));
+                    assert_eq!(list.find(|&x| x > 3), None);
+                    assert!(list.contains(&1));
+                    assert!(!list.contains(&4));
+                })
+            })
+        })
+    }
+
+    #[test]
+    fn lookup() {
+        StackList::push(None, ("x", 1), |list| {
+            StackList::push(Some(list), ("y", 2), |list| {
+                StackList::push(Some(list), ("x", 3), |list| {
+                    // The inner x shadows the outer one
+                    assert_eq!(list.lookup("x"), Some(&3));
+                    assert_eq!(list.lookup("y"), Some(&2));
+                    assert_eq!(list.lookup("z"), None);
+                })
+            })
+        })
+    }
+
+    #[test]
+    fn scope() {
+        use super::Scope;
+
+        let root = Scope::root();
+        assert_eq!(root.lookup("x"), None::<&i32>);
+        assert_eq!(root.depth(), 0);
+
+        let sum = root.bind(String::from("x"), 1, |scope| {
+            scope.bind(String::from("y"), 2, |inner| {
+                assert_eq!(inner.depth(), 2);
+                let shadowed = inner.bind(String::from("x"), 10, |innermost| {
+                    *innermost.lookup("x").unwrap()
+                });
+                assert_eq!(shadowed, 10);
+                inner.lookup("x").unwrap() + inner.lookup("y").unwrap()
+            })
+        });
+        assert_eq!(sum, 3);
+    }
+
+    #[test]
+    fn push_mut() {
+        StackList::push(None, 1, |list| {
+            StackList::push_mut(Some(list), 2, |list| {
+                list.data *= 10;
+                let (top, rest) = list.split_top_mut();
+                *top += rest.sum::<i32>();
+                assert_eq!(list.to_vec(), &[21, 1]);
+            })
+        })
+    }
+
+    #[test]
+    fn push_many() {
+        let mut frames: [Option<StackList<i32>>; 4] = Default::default();
+        StackList::push_many(None, 1..=4, &mut frames, |top| {
+            let top = top.unwrap();
+            assert_eq!(top.to_vec(), &[4, 3, 2, 1]);
+            assert_eq!(top.fold(0, |acc, x| acc * 10 + x), 4321);
+        });
+
+        StackList::push(None, 0, |list| {
+            let mut frames: [Option<StackList<i32>>; 3] = Default::default();
+            StackList::push_many(Some(list), 1..3, &mut frames, |top| {
+                assert_eq!(top.unwrap().to_vec(), &[2, 1, 0]);
+            });
+        });
+
+        let mut frames: [Option<StackList<i32>>; 1] = Default::default();
+        StackList::push_many(None, None, &mut frames, |top| assert!(top.is_none()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn push_many_out_of_frames() {
+        let mut frames: [Option<StackList<i32>>; 2] = Default::default();
+        StackList::push_many(None, 0..3, &mut frames, |_| ());
+    }
 }
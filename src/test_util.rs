@@ -0,0 +1,28 @@
+// Bits and pieces the tests across the crate share.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Bumps its counter when dropped, so a test can check every element was
+// dropped exactly once.
+pub(crate) struct DropCounter(pub(crate) Arc<AtomicUsize>);
+
+impl Drop for DropCounter {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+// A tiny deterministic generator, so the model tests are repeatable.
+pub(crate) struct Lcg(pub(crate) u64);
+
+impl Lcg {
+    // Something in `0..bound`, in whatever integer type `bound` is.
+    pub(crate) fn next<T: TryFrom<u64> + TryInto<u64>>(&mut self, bound: T) -> T {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let bound = bound.try_into().ok().expect("bound must not be negative");
+        T::try_from((self.0 >> 33) % bound).ok().unwrap()
+    }
+}
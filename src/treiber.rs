@@ -0,0 +1,365 @@
+// The Treiber stack: `second::List`, but with an atomic head so any number of
+// threads can push and pop through `&self`.
+//
+// Both ends of the usual trouble go through the hazard pointers in `hazard`:
+// a popper protects the head before reading `head.next`, and a node isn't
+// freed while anyone still protects it. So a node's address can't be reused
+// under a popper's feet, which is what rules out ABA on the head CAS.
+//
+// A popped node is retired to the domain rather than freed, so nobody ever
+// waits on anybody else. The popper moves the value out straight away though,
+// while a `peek_with` closure might still be looking at the node. Reading the
+// node's bytes is fine (they stay put until the domain frees it), but for a
+// type that owns something the popper could already have dropped it. So
+// `peek_with` is only offered for `T: Copy`, which owns nothing.
+//
+// That's a real restriction: a `TreiberStack<String>` or `TreiberStack<Vec<_>>`
+// has no way to look at its top without popping it. Lifting it would mean
+// either making `pop` wait until no `peek_with` is looking at the node before
+// moving the value out (and then nobody could call it lock-free any more), or
+// storing values behind a reference count and handing out clones, which is a
+// different stack. Stacks of indices, `&'static` references and other `Copy`
+// handles get `peek_with` as they are.
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+use crate::hazard::Domain;
+
+pub struct TreiberStack<T> {
+    head: AtomicPtr<Node<T>>,
+    len: AtomicUsize,
+    domain: Domain,
+    _boo: PhantomData<T>,
+}
+
+struct Node<T> {
+    elem: ManuallyDrop<T>,
+    next: *mut Node<T>,
+}
+
+impl<T> TreiberStack<T> {
+    pub fn new() -> Self {
+        TreiberStack {
+            head: AtomicPtr::new(ptr::null_mut()),
+            len: AtomicUsize::new(0),
+            domain: Domain::new(),
+            _boo: PhantomData,
+        }
+    }
+
+    pub fn push(&self, elem: T) {
+        let new = Box::into_raw(Box::new(Node {
+            elem: ManuallyDrop::new(elem),
+            next: ptr::null_mut(),
+        }));
+        // Count it first, so `len` never underflows when a pop beats us to it
+        self.len.fetch_add(1, Ordering::Relaxed);
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            // The node is still ours, nobody else can see `next` yet
+            unsafe { (*new).next = head };
+            match self
+                .head
+                .compare_exchange_weak(head, new, Ordering::Release, Ordering::Relaxed)
+            {
+                Ok(_) => return,
+                Err(actual) => head = actual,
+            }
+        }
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        let hazard = self.domain.hazard();
+        loop {
+            let head = hazard.protect(&self.head);
+            if head.is_null() {
+                return None;
+            }
+            let next = unsafe { (*head).next };
+            if self
+                .head
+                .compare_exchange(head, next, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                // It's unlinked and ours. Take the value, but leave the node
+                // to the domain, someone else might still be reading it.
+                hazard.clear();
+                self.len.fetch_sub(1, Ordering::Relaxed);
+                let elem = unsafe { ptr::read(&(*head).elem) };
+                unsafe { self.domain.retire(head) };
+                return Some(ManuallyDrop::into_inner(elem));
+            }
+        }
+    }
+
+    // Calls `f` on the top element, if there is one. It might get popped while
+    // `f` runs (even by `f` itself), but the node isn't freed until `f` is done.
+    // Only for `T: Copy`, see the top of the file for why.
+    pub fn peek_with<F, R>(&self, f: F) -> Option<R>
+    where
+        T: Copy,
+        F: FnOnce(&T) -> R,
+    {
+        let hazard = self.domain.hazard();
+        let head = hazard.protect(&self.head);
+        unsafe { head.as_ref() }.map(|node| f(&node.elem))
+    }
+
+    // Approximate: pushes and pops running right now may or may not be counted.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Acquire).is_null()
+    }
+
+    // Takes the whole stack in one swap and hands the elements back top first.
+    // Pushes that land after the swap stay on the stack.
+    pub fn drain(&self) -> Drain<'_, T> {
+        let head = self.head.swap(ptr::null_mut(), Ordering::AcqRel);
+        Drain { head, stack: self }
+    }
+}
+
+impl<T> Drop for TreiberStack<T> {
+    fn drop(&mut self) {
+        let mut cur = *self.head.get_mut();
+        while !cur.is_null() {
+            let mut node = unsafe { Box::from_raw(cur) };
+            unsafe { ManuallyDrop::drop(&mut node.elem) };
+            cur = node.next;
+        }
+    }
+}
+
+impl<T> Default for TreiberStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Extend<T> for TreiberStack<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.push(elem);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for TreiberStack<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut stack = Self::new();
+        stack.extend(iter);
+        stack
+    }
+}
+
+// Values only ever move between threads, except through `peek_with`, which
+// shares them.
+unsafe impl<T: Send> Send for TreiberStack<T> {}
+unsafe impl<T: Send + Sync> Sync for TreiberStack<T> {}
+
+pub struct Drain<'a, T> {
+    // A chain nobody else can reach any more, though `peek_with` or `pop`
+    // callers might still be holding a hazard on its first node.
+    head: *mut Node<T>,
+    stack: &'a TreiberStack<T>,
+}
+
+impl<'a, T> Iterator for Drain<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.head.is_null() {
+            return None;
+        }
+        let head = self.head;
+        self.stack.len.fetch_sub(1, Ordering::Relaxed);
+        let elem = unsafe { ptr::read(&(*head).elem) };
+        self.head = unsafe { (*head).next };
+        // Only the first node can still be protected, but retiring the rest
+        // too keeps this simple and they get freed on the next reclaim.
+        unsafe { self.stack.domain.retire(head) };
+        Some(ManuallyDrop::into_inner(elem))
+    }
+}
+
+impl<'a, T> Drop for Drain<'a, T> {
+    fn drop(&mut self) {
+        for _ in self.by_ref() {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TreiberStack;
+    use crate::test_util::DropCounter;
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    #[test]
+    fn basics() {
+        let stack = TreiberStack::new();
+        assert!(stack.is_empty());
+        assert_eq!(stack.pop(), None);
+        assert_eq!(stack.peek_with(|x: &i32| *x), None);
+
+        stack.push(1);
+        stack.push(2);
+        stack.push(3);
+        assert_eq!(stack.len(), 3);
+        assert_eq!(stack.peek_with(|x| x * 10), Some(30));
+        assert_eq!(stack.pop(), Some(3));
+        assert_eq!(stack.pop(), Some(2));
+
+        stack.push(4);
+        assert_eq!(stack.pop(), Some(4));
+        assert_eq!(stack.pop(), Some(1));
+        assert_eq!(stack.pop(), None);
+        assert_eq!(stack.len(), 0);
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn drain() {
+        let stack: TreiberStack<_> = (0..5).collect();
+        assert_eq!(stack.drain().collect::<Vec<_>>(), &[4, 3, 2, 1, 0]);
+        assert!(stack.is_empty());
+        assert_eq!(stack.len(), 0);
+
+        // Dropping a half used drain still drops the rest
+        let drops = Arc::new(AtomicUsize::new(0));
+        let stack = TreiberStack::new();
+        for _ in 0..10 {
+            stack.push(DropCounter(drops.clone()));
+        }
+        let mut drain = stack.drain();
+        drop(drain.next());
+        stack.push(DropCounter(drops.clone()));
+        drop(drain);
+        assert_eq!(drops.load(Ordering::SeqCst), 10);
+        assert_eq!(stack.len(), 1);
+        drop(stack);
+        assert_eq!(drops.load(Ordering::SeqCst), 11);
+    }
+
+    #[test]
+    fn pop_inside_peek() {
+        let stack = TreiberStack::new();
+        stack.push(1);
+        stack.push(2);
+        let peeked = stack.peek_with(|x| {
+            assert_eq!(stack.pop(), Some(2));
+            // Retired, not freed, so `x` is still good
+            assert_eq!(stack.domain.retired_len(), 1);
+            *x
+        });
+        assert_eq!(peeked, Some(2));
+        assert_eq!(stack.pop(), Some(1));
+        stack.domain.reclaim();
+        assert_eq!(stack.domain.retired_len(), 0);
+    }
+
+    #[test]
+    fn pop_doesnt_wait_for_peek() {
+        let stack = Arc::new(TreiberStack::new());
+        stack.push(7u64);
+        let peeking = Arc::new(Barrier::new(2));
+        let popped = Arc::new(Barrier::new(2));
+
+        let peeker = {
+            let stack = stack.clone();
+            let peeking = peeking.clone();
+            let popped = popped.clone();
+            thread::spawn(move || {
+                stack.peek_with(|x| {
+                    peeking.wait();
+                    popped.wait();
+                    *x
+                })
+            })
+        };
+
+        peeking.wait();
+        // The peeker is parked inside its closure, and the pop still goes through
+        assert_eq!(stack.pop(), Some(7));
+        popped.wait();
+        assert_eq!(peeker.join().unwrap(), Some(7));
+    }
+
+    #[test]
+    fn stress() {
+        const THREADS: usize = 8;
+        const PER_THREAD: usize = 20_000;
+
+        let stack = Arc::new(TreiberStack::new());
+        let barrier = Arc::new(Barrier::new(THREADS));
+        let threads: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let stack = stack.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    let mut popped = Vec::new();
+                    for i in 0..PER_THREAD {
+                        stack.push((t, i));
+                        if i % 3 != 0 {
+                            popped.extend(stack.pop());
+                        }
+                        if i % 1000 == 0 {
+                            stack.peek_with(|&(t, i)| assert!(t < THREADS && i < PER_THREAD));
+                        }
+                    }
+                    popped
+                })
+            })
+            .collect();
+
+        let mut all = HashSet::new();
+        for thread in threads {
+            for item in thread.join().unwrap() {
+                assert!(all.insert(item), "{:?} popped twice", item);
+            }
+        }
+        assert_eq!(stack.len(), THREADS * PER_THREAD - all.len());
+        for item in stack.drain() {
+            assert!(all.insert(item), "{:?} popped twice", item);
+        }
+        assert_eq!(all.len(), THREADS * PER_THREAD);
+        assert_eq!(stack.len(), 0);
+    }
+
+    #[test]
+    fn concurrent_drops() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let stack = Arc::new(TreiberStack::new());
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let stack = stack.clone();
+                let drops = drops.clone();
+                thread::spawn(move || {
+                    for i in 0..5_000 {
+                        stack.push(DropCounter(drops.clone()));
+                        stack.push(DropCounter(drops.clone()));
+                        drop(stack.pop());
+                        if t == 0 && i % 500 == 0 {
+                            stack.drain().for_each(drop);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let left = stack.len();
+        assert_eq!(drops.load(Ordering::SeqCst), 4 * 10_000 - left);
+        drop(stack);
+        assert_eq!(drops.load(Ordering::SeqCst), 4 * 10_000);
+    }
+}
@@ -0,0 +1,1053 @@
+// An unrolled linked list: `sixth::LinkedList`'s API, but each node holds up to
+// `N` elements in an inline array, so walking the list touches one node per
+// `N` elements instead of one per element.
+//
+// Inside a node the elements sit contiguously in `elems[start..start + len]`,
+// with free slots on either side. That lets both `push_front` and `push_back`
+// land in an existing node most of the time, and lets an insert in the middle
+// shift whichever side is shorter. A full node is split in half to make room,
+// and a node that empties out is unlinked. After a cursor removes an element,
+// the node gets merged with a neighbour if both fit in half a node, so heavy
+// deletion doesn't leave the list full of near-empty nodes.
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::ptr::{self, NonNull};
+
+type Link<T, const N: usize> = Option<NonNull<Node<T, N>>>;
+
+struct Node<T, const N: usize> {
+    front: Link<T, N>,
+    back: Link<T, N>,
+    start: usize,
+    len: usize,
+    elems: [MaybeUninit<T>; N],
+}
+
+impl<T, const N: usize> Node<T, N> {
+    // An empty node whose first element will go in at `start`.
+    fn alloc(start: usize) -> NonNull<Self> {
+        let node = Box::new(Node {
+            front: None,
+            back: None,
+            start,
+            len: 0,
+            elems: [const { MaybeUninit::uninit() }; N],
+        });
+        // SAFETY: boxes are never null
+        unsafe { NonNull::new_unchecked(Box::into_raw(node)) }
+    }
+
+    // Taken from the whole array, so it's good for copying runs of elements
+    // and not just the one.
+    fn slot(&mut self, i: usize) -> *mut T {
+        unsafe { self.elems.as_mut_ptr().cast::<T>().add(self.start + i) }
+    }
+
+    // Like `slot`, without borrowing the node. `IterMut` needs that, since it
+    // keeps coming back to a node it has already handed out `&mut T`s into.
+    //
+    // SAFETY: `node` must be live.
+    unsafe fn raw_slot(node: *mut Self, i: usize) -> *mut T {
+        let start = ptr::addr_of!((*node).start).read();
+        ptr::addr_of_mut!((*node).elems).cast::<T>().add(start + i)
+    }
+
+    fn get(&self, i: usize) -> &T {
+        debug_assert!(i < self.len);
+        unsafe { self.elems[self.start + i].assume_init_ref() }
+    }
+
+    fn get_mut(&mut self, i: usize) -> &mut T {
+        debug_assert!(i < self.len);
+        unsafe { self.elems[self.start + i].assume_init_mut() }
+    }
+
+    // Puts `elem` at position `i`, shifting the shorter side out of the way.
+    // The node must not be full.
+    fn insert(&mut self, i: usize, elem: T) {
+        debug_assert!(self.len < N && i <= self.len);
+        let room_back = self.start + self.len < N;
+        let room_front = self.start > 0;
+        unsafe {
+            if room_front && (i < self.len / 2 || !room_back) {
+                // Move `[0, i)` one slot towards the front
+                let base = self.elems.as_mut_ptr().add(self.start);
+                ptr::copy(base, base.sub(1), i);
+                self.start -= 1;
+            } else {
+                // Move `[i, len)` one slot towards the back
+                let base = self.elems.as_mut_ptr().add(self.start + i);
+                ptr::copy(base, base.add(1), self.len - i);
+            }
+            self.len += 1;
+            self.slot(i).write(elem);
+        }
+    }
+
+    // Takes the element at position `i` out, closing the gap from the shorter
+    // side.
+    fn remove(&mut self, i: usize) -> T {
+        debug_assert!(i < self.len);
+        unsafe {
+            let elem = self.slot(i).read();
+            if i < self.len / 2 {
+                let base = self.elems.as_mut_ptr().add(self.start);
+                ptr::copy(base, base.add(1), i);
+                self.start += 1;
+            } else {
+                let base = self.elems.as_mut_ptr().add(self.start + i);
+                ptr::copy(base.add(1), base, self.len - i - 1);
+            }
+            self.len -= 1;
+            elem
+        }
+    }
+
+    // Moves everything to the front of the array.
+    fn compact(&mut self) {
+        unsafe {
+            let base = self.elems.as_mut_ptr();
+            ptr::copy(base.add(self.start), base, self.len);
+        }
+        self.start = 0;
+    }
+}
+
+pub struct UnrolledList<T, const N: usize = 16> {
+    front: Link<T, N>,
+    back: Link<T, N>,
+    len: usize,
+    _boo: PhantomData<T>,
+}
+
+impl<T, const N: usize> UnrolledList<T, N> {
+    // `N` must be at least 2 (1 would just be a worse `sixth`), which is
+    // checked when the list is compiled.
+    pub fn new() -> Self {
+        const {
+            assert!(
+                N >= 2,
+                "unrolled list nodes need room for at least 2 elements"
+            )
+        };
+        UnrolledList {
+            front: None,
+            back: None,
+            len: 0,
+            _boo: PhantomData,
+        }
+    }
+
+    pub fn push_front(&mut self, elem: T) {
+        unsafe {
+            match self.front {
+                Some(node) if (*node.as_ptr()).len < N => (*node.as_ptr()).insert(0, elem),
+                _ => {
+                    // Leave the room at the front, for more `push_front`s
+                    let new = Node::alloc(N - 1);
+                    self.link_before(self.front, new);
+                    (*new.as_ptr()).insert(0, elem);
+                }
+            }
+        }
+        self.len += 1;
+    }
+
+    pub fn push_back(&mut self, elem: T) {
+        unsafe {
+            match self.back {
+                Some(node) if (*node.as_ptr()).len < N => {
+                    let node = &mut *node.as_ptr();
+                    node.insert(node.len, elem)
+                }
+                _ => {
+                    let new = Node::alloc(0);
+                    self.link_after(self.back, new);
+                    (*new.as_ptr()).insert(0, elem);
+                }
+            }
+        }
+        self.len += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.front.map(|node| unsafe { self.remove_at(node, 0) })
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.back
+            .map(|node| unsafe { self.remove_at(node, (*node.as_ptr()).len - 1) })
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        unsafe { self.front.map(|node| (*node.as_ptr()).get(0)) }
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        unsafe {
+            self.back.map(|node| {
+                let node = &*node.as_ptr();
+                node.get(node.len - 1)
+            })
+        }
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        unsafe { self.front.map(|node| (*node.as_ptr()).get_mut(0)) }
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        unsafe {
+            self.back.map(|node| {
+                let node = &mut *node.as_ptr();
+                node.get_mut(node.len - 1)
+            })
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+
+    pub fn iter(&self) -> Iter<'_, T, N> {
+        Iter {
+            front: self.front,
+            front_idx: 0,
+            back: self.back,
+            back_idx: self.back.map_or(0, |node| unsafe { (*node.as_ptr()).len }),
+            len: self.len,
+            _boo: PhantomData,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, N> {
+        IterMut {
+            front: self.front,
+            front_idx: 0,
+            back: self.back,
+            back_idx: self.back.map_or(0, |node| unsafe { (*node.as_ptr()).len }),
+            len: self.len,
+            _boo: PhantomData,
+        }
+    }
+
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T, N> {
+        CursorMut {
+            node: None,
+            offset: 0,
+            index: None,
+            list: self,
+        }
+    }
+
+    // Links `new` in after `at`, or at the front if `at` is `None`.
+    unsafe fn link_after(&mut self, at: Link<T, N>, new: NonNull<Node<T, N>>) {
+        let next = match at {
+            Some(at) => (*at.as_ptr()).back.replace(new),
+            None => self.front.replace(new),
+        };
+        (*new.as_ptr()).front = at;
+        (*new.as_ptr()).back = next;
+        match next {
+            Some(next) => (*next.as_ptr()).front = Some(new),
+            None => self.back = Some(new),
+        }
+    }
+
+    // Links `new` in before `at`, or at the back if `at` is `None`.
+    unsafe fn link_before(&mut self, at: Link<T, N>, new: NonNull<Node<T, N>>) {
+        let prev = match at {
+            Some(at) => (*at.as_ptr()).front,
+            None => self.back,
+        };
+        self.link_after(prev, new);
+    }
+
+    // Unlinks and frees an empty node.
+    unsafe fn free_node(&mut self, node: NonNull<Node<T, N>>) {
+        let boxed = Box::from_raw(node.as_ptr());
+        debug_assert_eq!(boxed.len, 0);
+        match boxed.front {
+            Some(prev) => (*prev.as_ptr()).back = boxed.back,
+            None => self.front = boxed.back,
+        }
+        match boxed.back {
+            Some(next) => (*next.as_ptr()).front = boxed.front,
+            None => self.back = boxed.front,
+        }
+    }
+
+    unsafe fn remove_at(&mut self, node: NonNull<Node<T, N>>, i: usize) -> T {
+        let elem = (*node.as_ptr()).remove(i);
+        if (*node.as_ptr()).len == 0 {
+            self.free_node(node);
+        }
+        self.len -= 1;
+        elem
+    }
+
+    // Inserts `elem` at position `i` of `node`, splitting the node if it's
+    // full. Returns where the new element ended up.
+    unsafe fn insert_at(
+        &mut self,
+        node: NonNull<Node<T, N>>,
+        i: usize,
+        elem: T,
+    ) -> (NonNull<Node<T, N>>, usize) {
+        let (mut node, mut i) = (node, i);
+        if (*node.as_ptr()).len == N {
+            // Move the back half into a fresh node right after this one
+            let half = N / 2;
+            let new = Node::alloc(0);
+            let old = &mut *node.as_ptr();
+            ptr::copy_nonoverlapping(old.slot(half), (*new.as_ptr()).slot(0), N - half);
+            (*new.as_ptr()).len = N - half;
+            old.len = half;
+            self.link_after(Some(node), new);
+            if i > half {
+                node = new;
+                i -= half;
+            }
+        }
+        (*node.as_ptr()).insert(i, elem);
+        self.len += 1;
+        (node, i)
+    }
+
+    // Folds the node after `node` into it, if both fit in half a node.
+    // Returns how many elements `node` had before, i.e. where the merged-in
+    // elements start, or `None` if nothing was merged.
+    unsafe fn merge_next(&mut self, node: NonNull<Node<T, N>>) -> Option<usize> {
+        let next = (*node.as_ptr()).back?;
+        let (this, other) = (&mut *node.as_ptr(), &mut *next.as_ptr());
+        if this.len + other.len > N / 2 {
+            return None;
+        }
+        let at = this.len;
+        this.compact();
+        // `compact` left `start` at 0, so `at` is right after the last element
+        ptr::copy_nonoverlapping(other.slot(0), this.slot(at), other.len);
+        this.len += other.len;
+        other.len = 0;
+        self.free_node(next);
+        Some(at)
+    }
+}
+
+impl<T, const N: usize> Drop for UnrolledList<T, N> {
+    fn drop(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+}
+
+impl<T, const N: usize> Default for UnrolledList<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for UnrolledList<T, N> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T, const N: usize> Extend<T> for UnrolledList<T, N> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.push_back(elem);
+        }
+    }
+}
+
+impl<T, const N: usize> FromIterator<T> for UnrolledList<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for UnrolledList<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T: PartialEq, const N: usize> PartialEq for UnrolledList<T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other)
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for UnrolledList<T, N> {}
+
+impl<T: PartialOrd, const N: usize> PartialOrd for UnrolledList<T, N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other)
+    }
+}
+
+impl<T: Ord, const N: usize> Ord for UnrolledList<T, N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other)
+    }
+}
+
+impl<T: Hash, const N: usize> Hash for UnrolledList<T, N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len.hash(state);
+        for elem in self {
+            elem.hash(state);
+        }
+    }
+}
+
+unsafe impl<T: Send, const N: usize> Send for UnrolledList<T, N> {}
+unsafe impl<T: Sync, const N: usize> Sync for UnrolledList<T, N> {}
+
+pub struct IntoIter<T, const N: usize>(UnrolledList<T, N>);
+
+// Both ends keep a node and a position in it. `back_idx` is one past the
+// element `next_back` will yield. `len` stops the two ends from crossing.
+pub struct Iter<'a, T, const N: usize> {
+    front: Link<T, N>,
+    front_idx: usize,
+    back: Link<T, N>,
+    back_idx: usize,
+    len: usize,
+    _boo: PhantomData<&'a T>,
+}
+
+pub struct IterMut<'a, T, const N: usize> {
+    front: Link<T, N>,
+    front_idx: usize,
+    back: Link<T, N>,
+    back_idx: usize,
+    len: usize,
+    _boo: PhantomData<&'a mut T>,
+}
+
+impl<T, const N: usize> IntoIterator for UnrolledList<T, N> {
+    type IntoIter = IntoIter<T, N>;
+    type Item = T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a UnrolledList<T, N> {
+    type IntoIter = Iter<'a, T, N>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut UnrolledList<T, N> {
+    type IntoIter = IterMut<'a, T, N>;
+    type Item = &'a mut T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T, const N: usize> Iterator for IntoIter<T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<T, const N: usize> DoubleEndedIterator for IntoIter<T, N> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.pop_back()
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for IntoIter<T, N> {}
+
+impl<'a, T, const N: usize> Iterator for Iter<'a, T, N> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.front.map(|node| unsafe {
+            let node = &*node.as_ptr();
+            let elem = node.get(self.front_idx);
+            self.front_idx += 1;
+            if self.front_idx == node.len {
+                self.front = node.back;
+                self.front_idx = 0;
+            }
+            self.len -= 1;
+            elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T, const N: usize> DoubleEndedIterator for Iter<'a, T, N> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.back.map(|node| unsafe {
+            let node = &*node.as_ptr();
+            self.back_idx -= 1;
+            let elem = node.get(self.back_idx);
+            if self.back_idx == 0 {
+                self.back = node.front;
+                self.back_idx = self.back.map_or(0, |prev| (*prev.as_ptr()).len);
+            }
+            self.len -= 1;
+            elem
+        })
+    }
+}
+
+impl<'a, T, const N: usize> ExactSizeIterator for Iter<'a, T, N> {}
+
+impl<'a, T, const N: usize> Iterator for IterMut<'a, T, N> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.front.map(|node| unsafe {
+            let node = node.as_ptr();
+            let len = ptr::addr_of!((*node).len).read();
+            let back = ptr::addr_of!((*node).back).read();
+            let elem = &mut *Node::raw_slot(node, self.front_idx);
+            self.front_idx += 1;
+            if self.front_idx == len {
+                self.front = back;
+                self.front_idx = 0;
+            }
+            self.len -= 1;
+            elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T, const N: usize> DoubleEndedIterator for IterMut<'a, T, N> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.back.map(|node| unsafe {
+            let node = node.as_ptr();
+            self.back_idx -= 1;
+            let front = ptr::addr_of!((*node).front).read();
+            let elem = &mut *Node::raw_slot(node, self.back_idx);
+            if self.back_idx == 0 {
+                self.back = front;
+                self.back_idx = self
+                    .back
+                    .map_or(0, |prev| ptr::addr_of!((*prev.as_ptr()).len).read());
+            }
+            self.len -= 1;
+            elem
+        })
+    }
+}
+
+impl<'a, T, const N: usize> ExactSizeIterator for IterMut<'a, T, N> {}
+
+unsafe impl<'a, T: Sync, const N: usize> Send for Iter<'a, T, N> {}
+unsafe impl<'a, T: Sync, const N: usize> Sync for Iter<'a, T, N> {}
+
+unsafe impl<'a, T: Send, const N: usize> Send for IterMut<'a, T, N> {}
+unsafe impl<'a, T: Sync, const N: usize> Sync for IterMut<'a, T, N> {}
+
+// Same shape as `sixth::CursorMut`: it's either on an element or on the ghost
+// position between the back and the front. On an element, it's at `offset`
+// inside `node`.
+pub struct CursorMut<'a, T, const N: usize> {
+    node: Link<T, N>,
+    offset: usize,
+    index: Option<usize>,
+    list: &'a mut UnrolledList<T, N>,
+}
+
+impl<'a, T, const N: usize> CursorMut<'a, T, N> {
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    pub fn move_next(&mut self) {
+        match self.node {
+            Some(node) => {
+                self.offset += 1;
+                self.fix_forward(node);
+                if self.node.is_some() {
+                    *self.index.as_mut().unwrap() += 1;
+                }
+            }
+            None => {
+                self.node = self.list.front;
+                self.offset = 0;
+                self.index = self.node.map(|_| 0);
+            }
+        }
+    }
+
+    pub fn move_prev(&mut self) {
+        match self.node {
+            Some(_) if self.offset > 0 => {
+                self.offset -= 1;
+                *self.index.as_mut().unwrap() -= 1;
+            }
+            Some(node) => unsafe {
+                self.node = (*node.as_ptr()).front;
+                match self.node {
+                    Some(prev) => {
+                        self.offset = (*prev.as_ptr()).len - 1;
+                        *self.index.as_mut().unwrap() -= 1;
+                    }
+                    None => self.index = None,
+                }
+            },
+            None => unsafe {
+                self.node = self.list.back;
+                if let Some(back) = self.node {
+                    self.offset = (*back.as_ptr()).len - 1;
+                    self.index = Some(self.list.len - 1);
+                }
+            },
+        }
+    }
+
+    pub fn current(&mut self) -> Option<&mut T> {
+        unsafe { self.node.map(|node| (*node.as_ptr()).get_mut(self.offset)) }
+    }
+
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        unsafe {
+            let (node, offset) = match self.node {
+                Some(node) if self.offset + 1 < (*node.as_ptr()).len => {
+                    (Some(node), self.offset + 1)
+                }
+                Some(node) => ((*node.as_ptr()).back, 0),
+                None => (self.list.front, 0),
+            };
+            node.map(|node| (*node.as_ptr()).get_mut(offset))
+        }
+    }
+
+    pub fn peek_prev(&mut self) -> Option<&mut T> {
+        unsafe {
+            let (node, offset) = match self.node {
+                Some(node) if self.offset > 0 => (Some(node), self.offset - 1),
+                Some(node) => {
+                    let prev = (*node.as_ptr()).front;
+                    (prev, prev.map_or(0, |prev| (*prev.as_ptr()).len - 1))
+                }
+                None => {
+                    let back = self.list.back;
+                    (back, back.map_or(0, |back| (*back.as_ptr()).len - 1))
+                }
+            };
+            node.map(|node| (*node.as_ptr()).get_mut(offset))
+        }
+    }
+
+    // Inserts before the current element. At the ghost, that's the back of
+    // the list.
+    pub fn insert_before(&mut self, elem: T) {
+        match self.node {
+            Some(node) => unsafe {
+                let (node, offset) = self.list.insert_at(node, self.offset, elem);
+                // We're right after the new element
+                self.offset = offset + 1;
+                self.fix_forward(node);
+                *self.index.as_mut().unwrap() += 1;
+            },
+            None => self.list.push_back(elem),
+        }
+    }
+
+    // Inserts after the current element. At the ghost, that's the front of
+    // the list.
+    pub fn insert_after(&mut self, elem: T) {
+        match self.node {
+            Some(node) => unsafe {
+                let (node, offset) = self.list.insert_at(node, self.offset + 1, elem);
+                // We're right before the new element. A split only moves the
+                // insert into the new node when it lands past the old node's
+                // first half, so it never ends up at the new node's start.
+                debug_assert!(offset > 0);
+                self.node = Some(node);
+                self.offset = offset - 1;
+            },
+            None => self.list.push_front(elem),
+        }
+    }
+
+    // Removes the current element and moves on to the next one.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let node = self.node?;
+        unsafe {
+            let elem = (*node.as_ptr()).remove(self.offset);
+            self.list.len -= 1;
+            if (*node.as_ptr()).len == 0 {
+                self.node = (*node.as_ptr()).back;
+                self.offset = 0;
+                self.list.free_node(node);
+            } else {
+                // Try folding into the previous node, then the next one into
+                // whatever we ended up in
+                let mut node = node;
+                if let Some(prev) = (*node.as_ptr()).front {
+                    if let Some(at) = self.list.merge_next(prev) {
+                        node = prev;
+                        self.offset += at;
+                    }
+                }
+                self.list.merge_next(node);
+                self.fix_forward(node);
+            }
+            if self.node.is_none() {
+                self.index = None;
+            }
+            Some(elem)
+        }
+    }
+
+    // If `offset` ran off the end of `node`, move to the start of the next one
+    // (or to the ghost).
+    fn fix_forward(&mut self, node: NonNull<Node<T, N>>) {
+        unsafe {
+            if self.offset == (*node.as_ptr()).len {
+                self.node = (*node.as_ptr()).back;
+                self.offset = 0;
+                if self.node.is_none() {
+                    self.index = None;
+                }
+            } else {
+                self.node = Some(node);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::UnrolledList;
+    use crate::test_util::Lcg;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    // Checks the links and counts against the elements, node by node.
+    fn check<T, const N: usize>(list: &UnrolledList<T, N>) {
+        let mut count = 0;
+        let mut prev = None;
+        let mut cur = list.front;
+        while let Some(node) = cur {
+            let node_ref = unsafe { &*node.as_ptr() };
+            assert_eq!(node_ref.front, prev);
+            assert!(node_ref.len > 0 && node_ref.start + node_ref.len <= N);
+            count += node_ref.len;
+            prev = cur;
+            cur = node_ref.back;
+        }
+        assert_eq!(list.back, prev);
+        assert_eq!(count, list.len());
+    }
+
+    #[test]
+    fn basics() {
+        let mut list = UnrolledList::<i32, 4>::new();
+        assert_eq!(list.pop_front(), None);
+        assert_eq!(list.pop_back(), None);
+
+        for i in 0..10 {
+            list.push_back(i);
+        }
+        for i in 1..=10 {
+            list.push_front(-i);
+        }
+        check(&list);
+        assert_eq!(list.len(), 20);
+        assert_eq!(list.front(), Some(&-10));
+        assert_eq!(list.back(), Some(&9));
+
+        *list.front_mut().unwrap() = 100;
+        *list.back_mut().unwrap() = 200;
+        assert_eq!(list.pop_front(), Some(100));
+        assert_eq!(list.pop_back(), Some(200));
+        assert_eq!(list.pop_back(), Some(8));
+        check(&list);
+
+        list.clear();
+        assert!(list.is_empty());
+        check(&list);
+    }
+
+    #[test]
+    fn iter_mut_both_ends_of_one_node() {
+        let mut list = UnrolledList::<i32, 8>::new();
+        list.extend(0..4);
+        let mut iter = list.iter_mut();
+        // All in the same node, and both stay live while the iterator keeps
+        // reading that node
+        let first = iter.next().unwrap();
+        let last = iter.next_back().unwrap();
+        let middle: Vec<_> = iter.collect();
+        *first += 10;
+        *last += 10;
+        for elem in middle {
+            *elem += 100;
+        }
+        assert_eq!(
+            list.iter().copied().collect::<Vec<_>>(),
+            &[10, 101, 102, 13]
+        );
+    }
+
+    #[test]
+    fn deque_model() {
+        let mut rng = Lcg(1);
+        let mut list = UnrolledList::<usize, 5>::new();
+        let mut model = VecDeque::new();
+        for i in 0..5_000 {
+            match rng.next(4) {
+                0 => {
+                    list.push_front(i);
+                    model.push_front(i);
+                }
+                1 => {
+                    list.push_back(i);
+                    model.push_back(i);
+                }
+                2 => assert_eq!(list.pop_front(), model.pop_front()),
+                _ => assert_eq!(list.pop_back(), model.pop_back()),
+            }
+        }
+        check(&list);
+        assert!(list.iter().eq(model.iter()));
+        assert!(list.iter().rev().eq(model.iter().rev()));
+    }
+
+    #[test]
+    fn iterators() {
+        let mut list: UnrolledList<i32, 3> = (0..10).collect();
+
+        let mut iter = list.iter();
+        assert_eq!(iter.len(), 10);
+        assert_eq!(iter.next(), Some(&0));
+        assert_eq!(iter.next_back(), Some(&9));
+        assert_eq!(iter.next_back(), Some(&8));
+        assert_eq!(iter.len(), 7);
+        assert_eq!(iter.collect::<Vec<_>>(), &[&1, &2, &3, &4, &5, &6, &7]);
+
+        // Meeting in the middle of a node
+        let mut iter = list.iter_mut();
+        for _ in 0..4 {
+            iter.next();
+        }
+        for _ in 0..5 {
+            iter.next_back();
+        }
+        assert_eq!(iter.next().map(|x| *x), Some(4));
+        assert_eq!(iter.next_back(), None);
+
+        for elem in &mut list {
+            *elem *= 2;
+        }
+        let mut into = list.clone().into_iter();
+        assert_eq!(into.len(), 10);
+        assert_eq!(into.next_back(), Some(18));
+        assert_eq!(into.next(), Some(0));
+        assert_eq!(format!("{:?}", list), "[0, 2, 4, 6, 8, 10, 12, 14, 16, 18]");
+        assert_eq!(list, (0..10).map(|x| x * 2).collect());
+    }
+
+    #[test]
+    fn cursor() {
+        let mut list: UnrolledList<i32, 4> = (0..4).collect();
+        let mut cursor = list.cursor_mut();
+        assert_eq!(cursor.index(), None);
+        assert_eq!(cursor.peek_next(), Some(&mut 0));
+        assert_eq!(cursor.peek_prev(), Some(&mut 3));
+
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&mut 1));
+        // The node is full, so this splits it
+        cursor.insert_before(10);
+        assert_eq!(cursor.index(), Some(2));
+        assert_eq!(cursor.current(), Some(&mut 1));
+        assert_eq!(cursor.peek_prev(), Some(&mut 10));
+        cursor.insert_after(20);
+        assert_eq!(cursor.current(), Some(&mut 1));
+        assert_eq!(cursor.peek_next(), Some(&mut 20));
+
+        assert_eq!(cursor.remove_current(), Some(1));
+        assert_eq!(cursor.current(), Some(&mut 20));
+        assert_eq!(cursor.index(), Some(2));
+
+        cursor.move_prev();
+        cursor.move_prev();
+        cursor.move_prev();
+        assert_eq!(cursor.index(), None);
+        cursor.insert_after(-1);
+        cursor.insert_before(99);
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&mut 99));
+        assert_eq!(cursor.index(), Some(6));
+        assert_eq!(cursor.remove_current(), Some(99));
+        assert_eq!(cursor.index(), None);
+
+        check(&list);
+        assert_eq!(
+            list.iter().copied().collect::<Vec<_>>(),
+            &[-1, 0, 10, 20, 2, 3]
+        );
+    }
+
+    #[test]
+    fn cursor_model() {
+        let mut rng = Lcg(7);
+        let mut list = UnrolledList::<usize, 4>::new();
+        let mut model: Vec<usize> = Vec::new();
+        let mut cursor = list.cursor_mut();
+        // `None` is the ghost
+        let mut pos: Option<usize> = None;
+
+        for i in 0..5_000 {
+            match rng.next(6) {
+                0 => {
+                    cursor.move_next();
+                    pos = match pos {
+                        Some(p) if p + 1 < model.len() => Some(p + 1),
+                        Some(_) => None,
+                        None if model.is_empty() => None,
+                        None => Some(0),
+                    };
+                }
+                1 => {
+                    cursor.move_prev();
+                    pos = match pos {
+                        Some(0) => None,
+                        Some(p) => Some(p - 1),
+                        None => model.len().checked_sub(1),
+                    };
+                }
+                2 => {
+                    cursor.insert_before(i);
+                    match pos {
+                        Some(p) => {
+                            model.insert(p, i);
+                            pos = Some(p + 1);
+                        }
+                        None => model.push(i),
+                    }
+                }
+                3 => {
+                    cursor.insert_after(i);
+                    match pos {
+                        Some(p) => model.insert(p + 1, i),
+                        None => model.insert(0, i),
+                    }
+                }
+                _ => {
+                    let removed = cursor.remove_current();
+                    match pos {
+                        Some(p) => {
+                            assert_eq!(removed, Some(model.remove(p)));
+                            if p == model.len() {
+                                pos = None;
+                            }
+                        }
+                        None => assert_eq!(removed, None),
+                    }
+                }
+            }
+            assert_eq!(cursor.index(), pos);
+            assert_eq!(cursor.current().copied(), pos.map(|p| model[p]));
+        }
+
+        check(&list);
+        assert!(list.iter().eq(model.iter()));
+    }
+
+    #[test]
+    fn merges_after_removal() {
+        let mut list: UnrolledList<i32, 8> = (0..64).collect();
+        let mut cursor = list.cursor_mut();
+        cursor.move_next();
+        // Keep every 8th element
+        let mut i = 0;
+        while cursor.current().is_some() {
+            if i % 8 == 0 {
+                cursor.move_next();
+            } else {
+                cursor.remove_current();
+            }
+            i += 1;
+        }
+        check(&list);
+        assert_eq!(
+            list.iter().copied().collect::<Vec<_>>(),
+            &[0, 8, 16, 24, 32, 40, 48, 56]
+        );
+        // 8 elements fit in 2 half-full nodes, not 8 nodes of 1
+        let mut nodes = 0;
+        let mut cur = list.front;
+        while let Some(node) = cur {
+            nodes += 1;
+            cur = unsafe { (*node.as_ptr()).back };
+        }
+        assert!(nodes <= 2, "{} nodes", nodes);
+    }
+
+    #[test]
+    fn drops() {
+        let counter = Rc::new(());
+        let mut list = UnrolledList::<_, 4>::new();
+        for _ in 0..10 {
+            list.push_back(counter.clone());
+            list.push_front(counter.clone());
+        }
+        assert_eq!(Rc::strong_count(&counter), 21);
+        let mut cursor = list.cursor_mut();
+        cursor.move_next();
+        cursor.move_next();
+        drop(cursor.remove_current());
+        cursor.insert_after(counter.clone());
+        drop(list.pop_back());
+        assert_eq!(Rc::strong_count(&counter), 20);
+        drop(list);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+}
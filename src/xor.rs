@@ -0,0 +1,497 @@
+// An XOR linked deque. `sixth::Node` keeps a `front` and a `back` pointer; here
+// each node keeps just one word, the XOR of its neighbours' addresses. Walking
+// the list means remembering where you came from: the next node's address is
+// `both ^ address of the previous node`. The ends have a null (0) neighbour,
+// so the first node's `both` is simply the address of the second.
+//
+// Because nothing points "forwards" or "backwards" in particular, the list is
+// symmetric: every operation on the front is the same as the one on the back
+// with `head` and `tail` swapped, and `reverse` is just that swap.
+//
+// XORing addresses throws away pointer provenance, so every node's address is
+// exposed when it's allocated and the pointers are rebuilt from exposed
+// addresses (`expose_provenance` / `with_exposed_provenance_mut`). That's the
+// model Miri checks this kind of trick against; plain `as usize` casts and
+// back would not be.
+use std::fmt;
+use std::marker::PhantomData;
+use std::ptr;
+
+struct Node<T> {
+    // addr(previous) ^ addr(next), with null as 0
+    both: usize,
+    elem: T,
+}
+
+fn addr<T>(node: *mut Node<T>) -> usize {
+    node.expose_provenance()
+}
+
+// The neighbour of `node` that isn't `from`.
+unsafe fn other<T>(node: *mut Node<T>, from: *mut Node<T>) -> *mut Node<T> {
+    ptr::with_exposed_provenance_mut((*node).both ^ addr(from))
+}
+
+pub struct XorList<T> {
+    head: *mut Node<T>,
+    tail: *mut Node<T>,
+    len: usize,
+    _boo: PhantomData<T>,
+}
+
+// Pushes onto the `first` end. Called with `head, tail` for the front and
+// `tail, head` for the back.
+fn push<T>(first: &mut *mut Node<T>, last: &mut *mut Node<T>, elem: T) {
+    let new = Box::into_raw(Box::new(Node {
+        both: addr(*first),
+        elem,
+    }));
+    if first.is_null() {
+        *last = new;
+    } else {
+        // The old first's null neighbour becomes `new`
+        unsafe { (**first).both ^= addr(new) };
+    }
+    *first = new;
+}
+
+fn pop<T>(first: &mut *mut Node<T>, last: &mut *mut Node<T>) -> Option<T> {
+    if first.is_null() {
+        return None;
+    }
+    unsafe {
+        // One neighbour is null, so `both` is the other one. Finish with the
+        // raw pointer before turning it back into a box.
+        let old = *first;
+        let next = other(old, ptr::null_mut());
+        if next.is_null() {
+            *last = ptr::null_mut();
+        } else {
+            (*next).both ^= addr(old);
+        }
+        *first = next;
+        Some(Box::from_raw(old).elem)
+    }
+}
+
+impl<T> XorList<T> {
+    pub fn new() -> Self {
+        XorList {
+            head: ptr::null_mut(),
+            tail: ptr::null_mut(),
+            len: 0,
+            _boo: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push_front(&mut self, elem: T) {
+        push(&mut self.head, &mut self.tail, elem);
+        self.len += 1;
+    }
+
+    pub fn push_back(&mut self, elem: T) {
+        push(&mut self.tail, &mut self.head, elem);
+        self.len += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        let elem = pop(&mut self.head, &mut self.tail)?;
+        self.len -= 1;
+        Some(elem)
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        let elem = pop(&mut self.tail, &mut self.head)?;
+        self.len -= 1;
+        Some(elem)
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        unsafe { self.head.as_ref().map(|node| &node.elem) }
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        unsafe { self.head.as_mut().map(|node| &mut node.elem) }
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        unsafe { self.tail.as_ref().map(|node| &node.elem) }
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        unsafe { self.tail.as_mut().map(|node| &mut node.elem) }
+    }
+
+    // O(1): the links don't have a direction, so the old back is now the front.
+    pub fn reverse(&mut self) {
+        std::mem::swap(&mut self.head, &mut self.tail);
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            front: self.head,
+            front_prev: ptr::null_mut(),
+            back: self.tail,
+            back_next: ptr::null_mut(),
+            len: self.len,
+            _boo: PhantomData,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            front: self.head,
+            front_prev: ptr::null_mut(),
+            back: self.tail,
+            back_next: ptr::null_mut(),
+            len: self.len,
+            _boo: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for XorList<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T> Default for XorList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Clone for XorList<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T> Extend<T> for XorList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.push_back(elem);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for XorList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for XorList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for XorList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other)
+    }
+}
+
+impl<T: Eq> Eq for XorList<T> {}
+
+unsafe impl<T: Send> Send for XorList<T> {}
+unsafe impl<T: Sync> Sync for XorList<T> {}
+
+pub struct IntoIter<T>(XorList<T>);
+
+// Each end remembers the node it came from, that's the only way to take the
+// next step.
+pub struct Iter<'a, T> {
+    front: *mut Node<T>,
+    front_prev: *mut Node<T>,
+    back: *mut Node<T>,
+    back_next: *mut Node<T>,
+    len: usize,
+    _boo: PhantomData<&'a T>,
+}
+
+pub struct IterMut<'a, T> {
+    front: *mut Node<T>,
+    front_prev: *mut Node<T>,
+    back: *mut Node<T>,
+    back_next: *mut Node<T>,
+    len: usize,
+    _boo: PhantomData<&'a mut T>,
+}
+
+// One step from `cur`, away from `from`.
+unsafe fn step<T>(cur: &mut *mut Node<T>, from: &mut *mut Node<T>) -> *mut Node<T> {
+    let node = *cur;
+    *cur = other(node, *from);
+    *from = node;
+    node
+}
+
+impl<T> IntoIterator for XorList<T> {
+    type IntoIter = IntoIter<T>;
+    type Item = T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a XorList<T> {
+    type IntoIter = Iter<'a, T>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut XorList<T> {
+    type IntoIter = IterMut<'a, T>;
+    type Item = &'a mut T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe { Some(&(*step(&mut self.front, &mut self.front_prev)).elem) }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe { Some(&(*step(&mut self.back, &mut self.back_next)).elem) }
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe { Some(&mut (*step(&mut self.front, &mut self.front_prev)).elem) }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe { Some(&mut (*step(&mut self.back, &mut self.back_next)).elem) }
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {}
+
+unsafe impl<'a, T: Sync> Send for Iter<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for Iter<'a, T> {}
+unsafe impl<'a, T: Send> Send for IterMut<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for IterMut<'a, T> {}
+
+#[cfg(test)]
+mod test {
+    use super::{Node, XorList};
+    use crate::test_util::{DropCounter, Lcg};
+    use std::collections::VecDeque;
+    use std::mem::size_of;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn basics() {
+        let mut list = XorList::new();
+        assert_eq!(list.pop_front(), None);
+        assert_eq!(list.pop_back(), None);
+
+        list.push_back(2);
+        list.push_front(1);
+        list.push_back(3);
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.front(), Some(&1));
+        assert_eq!(list.back(), Some(&3));
+        *list.front_mut().unwrap() = 10;
+        *list.back_mut().unwrap() = 30;
+
+        assert_eq!(list.pop_front(), Some(10));
+        assert_eq!(list.pop_back(), Some(30));
+        assert_eq!(list.pop_back(), Some(2));
+        assert_eq!(list.pop_front(), None);
+        assert!(list.is_empty());
+
+        // Refilling after running dry
+        list.push_front(4);
+        assert_eq!(list.pop_back(), Some(4));
+    }
+
+    #[test]
+    fn one_word_of_links() {
+        assert_eq!(
+            size_of::<Node<u64>>(),
+            size_of::<u64>() + size_of::<usize>()
+        );
+    }
+
+    #[test]
+    fn iter_both_ways() {
+        let mut list: XorList<_> = (0..6).collect();
+        assert_eq!(
+            list.iter().copied().collect::<Vec<_>>(),
+            &[0, 1, 2, 3, 4, 5]
+        );
+        assert_eq!(
+            list.iter().rev().copied().collect::<Vec<_>>(),
+            &[5, 4, 3, 2, 1, 0]
+        );
+
+        // Ends meeting in the middle
+        let mut iter = list.iter();
+        assert_eq!(iter.next(), Some(&0));
+        assert_eq!(iter.next_back(), Some(&5));
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next_back(), Some(&4));
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.next_back(), Some(&3));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        for elem in list.iter_mut().rev().step_by(2) {
+            *elem *= 10;
+        }
+        assert_eq!(list, [0, 10, 2, 30, 4, 50].into_iter().collect());
+        assert_eq!(format!("{:?}", list), "[0, 10, 2, 30, 4, 50]");
+        assert_eq!(
+            list.clone().into_iter().rev().collect::<Vec<_>>(),
+            &[50, 4, 30, 2, 10, 0]
+        );
+    }
+
+    #[test]
+    fn reverse() {
+        let mut list: XorList<_> = (0..4).collect();
+        list.reverse();
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), &[3, 2, 1, 0]);
+        list.push_front(4);
+        list.push_back(-1);
+        assert_eq!(list.pop_front(), Some(4));
+        list.reverse();
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), &[-1, 0, 1, 2, 3]);
+
+        let mut empty = XorList::<i32>::new();
+        empty.reverse();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn model() {
+        let mut rng = Lcg(7);
+        let mut list = XorList::new();
+        let mut model = VecDeque::new();
+        for i in 0..2_000 {
+            match rng.next(6) {
+                0 => {
+                    list.push_front(i);
+                    model.push_front(i);
+                }
+                1 => {
+                    list.push_back(i);
+                    model.push_back(i);
+                }
+                2 => assert_eq!(list.pop_front(), model.pop_front()),
+                3 => assert_eq!(list.pop_back(), model.pop_back()),
+                4 => {
+                    list.reverse();
+                    model.make_contiguous().reverse();
+                }
+                _ => {
+                    assert!(list.iter().eq(model.iter()));
+                    assert!(list.iter().rev().eq(model.iter().rev()));
+                }
+            }
+            assert_eq!(list.len(), model.len());
+            assert_eq!(list.front(), model.front());
+            assert_eq!(list.back(), model.back());
+        }
+    }
+
+    #[test]
+    fn drops_everything_once() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let mut list = XorList::new();
+        for _ in 0..10 {
+            list.push_back(DropCounter(drops.clone()));
+        }
+        drop(list.pop_front());
+        drop(list.pop_back());
+        assert_eq!(drops.load(Ordering::SeqCst), 2);
+
+        let mut iter = list.into_iter();
+        drop(iter.next());
+        assert_eq!(drops.load(Ordering::SeqCst), 3);
+        drop(iter);
+        assert_eq!(drops.load(Ordering::SeqCst), 10);
+    }
+}